@@ -0,0 +1,60 @@
+/// Groups file extensions into broad categories that share a colour.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileCategory {
+    Archive,
+    Image,
+    Audio,
+    Video,
+    Document,
+    Source,
+    Config,
+    Temporary,
+}
+
+impl FileCategory {
+    /// Categorises a lowercase file name by its extension, or by the trailing `~`
+    /// and `#...#` markers editors use for backup and autosave files.
+    pub fn from_file_name<'a>(
+        name: &str,
+        extensions: impl IntoIterator<Item = &'a str>,
+    ) -> Option<Self> {
+        if name.ends_with('~') || (name.starts_with('#') && name.ends_with('#')) {
+            return Some(FileCategory::Temporary);
+        }
+
+        extensions.into_iter().find_map(Self::from_extension)
+    }
+
+    /// Categorises a lowercase extension, without the leading dot.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        let category =
+            match extension {
+                "zip" | "tar" | "gz" | "tgz" | "xz" | "txz" | "bz2" | "tbz2" | "zst" | "lz4"
+                | "7z" | "rar" | "deb" | "rpm" | "jar" | "war" | "apk" | "dmg" | "iso"
+                | "tar.gz" | "tar.xz" | "tar.bz2" | "tar.zst" => FileCategory::Archive,
+                "png" | "jpg" | "jpeg" | "gif" | "bmp" | "svg" | "webp" | "ico" | "tif"
+                | "tiff" | "heic" | "avif" | "psd" | "raw" => FileCategory::Image,
+                "mp3" | "flac" | "wav" | "ogg" | "m4a" | "opus" | "aac" | "aiff" | "mid"
+                | "midi" => FileCategory::Audio,
+                "mp4" | "mkv" | "avi" | "mov" | "webm" | "wmv" | "flv" | "m4v" | "mpeg" | "mpg" => {
+                    FileCategory::Video
+                }
+                "md" | "markdown" | "rst" | "txt" | "pdf" | "doc" | "docx" | "odt" | "rtf"
+                | "tex" | "xls" | "xlsx" | "ods" | "csv" | "tsv" | "ppt" | "pptx" | "odp"
+                | "epub" => FileCategory::Document,
+                "rs" | "c" | "h" | "cc" | "cpp" | "cxx" | "hh" | "hpp" | "go" | "py" | "pyi"
+                | "js" | "mjs" | "cjs" | "ts" | "mts" | "cts" | "jsx" | "tsx" | "java" | "kt"
+                | "kts" | "rb" | "php" | "lua" | "swift" | "hs" | "ex" | "exs" | "zig" | "nix"
+                | "sh" | "bash" | "zsh" | "fish" | "html" | "htm" | "css" | "scss" | "sass"
+                | "sql" | "vim" => FileCategory::Source,
+                "toml" | "yaml" | "yml" | "json" | "jsonc" | "ini" | "cfg" | "conf" | "xml"
+                | "env" | "lock" | "properties" => FileCategory::Config,
+                "tmp" | "temp" | "bak" | "swp" | "swo" | "orig" | "rej" | "log" | "o" | "obj"
+                | "pyc" | "class" | "part" | "crdownload" => FileCategory::Temporary,
+                _ => return None,
+            };
+
+        Some(category)
+    }
+}
@@ -1,7 +1,11 @@
 use crate::sort::{SortField, SortOptions, SortOrder};
+use crate::terminal::When;
 /// Defines CLI arguments using `clap`.
-use clap::{ArgGroup, Parser};
-use std::ffi::OsString;
+use clap::builder::RangedU64ValueParser;
+use clap::parser::ValueSource;
+use clap::{Arg, ArgGroup, ArgMatches, Command, CommandFactory, Parser, ValueEnum};
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -9,16 +13,21 @@ use std::ffi::OsString;
     version,
     about,
     long_about = None,
+    args_override_self = true,
     group(
         ArgGroup::new("display_mode")
             .args(["long", "one_per_line"])
             .multiple(false)
     ),
-    after_help = "Examples:\n  lx -la\n  lx -lr\n  lx -alr path/to/dir"
+    after_help = "Examples:\n  lx -la\n  lx -lr\n  lx -alr path/to/dir\n  lx src tests *.toml\n\n\
+                  Exit status:\n  0  success\n  1  something inside a listing could not be read\n  \
+                  2  a target does not exist\n  3  permission to read a target was denied\n  \
+                  4  a target could not be read for another reason\n  5  a config problem\n  \
+                  6  an invalid --exclude pattern\n  7  the listing could not be written"
 )]
 pub struct Args {
-    #[arg(default_value = ".")]
-    pub target: String,
+    #[arg(value_name = "TARGET", default_value = ".")]
+    pub targets: Vec<String>,
 
     #[arg(short, long, help = "Use a long listing format")]
     pub long: bool,
@@ -34,7 +43,7 @@ pub struct Args {
         short = 'f',
         long = "files",
         help = "Show only files",
-        conflicts_with = "recursive"
+        conflicts_with_all = ["recursive", "level"]
     )]
     pub files: bool,
 
@@ -42,7 +51,7 @@ pub struct Args {
         short = 'd',
         long = "directories",
         help = "Show only directories",
-        conflicts_with = "recursive"
+        conflicts_with_all = ["recursive", "level"]
     )]
     pub directories: bool,
 
@@ -76,7 +85,7 @@ pub struct Args {
     #[arg(
         short = '1',
         help = "Force single column output",
-        conflicts_with = "recursive"
+        conflicts_with_all = ["recursive", "level"]
     )]
     pub one_per_line: bool,
 
@@ -84,16 +93,156 @@ pub struct Args {
         short = 'c',
         long,
         help = "Use compact columns with a limited number of rows",
-        conflicts_with_all = ["long", "one_per_line", "recursive"]
+        conflicts_with_all = ["long", "one_per_line", "recursive", "level"]
     )]
     pub compact: bool,
 
+    #[arg(
+        short = 'C',
+        long,
+        help = "Fit entries into as many columns as the terminal width allows",
+        conflicts_with_all = ["long", "one_per_line", "compact", "recursive", "level"]
+    )]
+    pub grid: bool,
+
     #[arg(
         short = 'r',
         long = "recursive",
         help = "Show directory tree recursively"
     )]
     pub recursive: bool,
+
+    #[arg(
+        short = 'L',
+        long,
+        value_name = "N",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..),
+        help = "Descend at most N directories deep in the tree (implies -r)"
+    )]
+    pub level: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with_all = ["long", "one_per_line", "compact", "grid"],
+        help = "Lay out entries as short, compact, one-per-line, long or tree"
+    )]
+    pub format: Option<String>,
+
+    #[arg(
+        long,
+        help = "Show git status markers, or a git column with -l, for entries in a repository"
+    )]
+    pub git: bool,
+
+    #[arg(
+        long = "git-ignore",
+        overrides_with = "no_git_ignore",
+        help = "Hide entries matched by .gitignore, .ignore and git exclude rules"
+    )]
+    pub git_ignore: bool,
+
+    #[arg(
+        long = "no-git-ignore",
+        overrides_with = "git_ignore",
+        help = "Show ignored entries even when the config enables git_ignore"
+    )]
+    pub no_git_ignore: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "always",
+        help = "Colour output: auto (only on a terminal), always, or never"
+    )]
+    pub color: Option<When>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "always",
+        help = "Show icons: auto (only on a terminal), always, or never"
+    )]
+    pub icons: Option<When>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = OutputFormat::Text,
+        conflicts_with_all = ["long", "one_per_line", "compact", "grid", "format"],
+        help = "Print text, a JSON document, or one JSON object per line"
+    )]
+    pub output: OutputFormat,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Read the config from PATH instead of the default location"
+    )]
+    pub config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Apply the [profiles.NAME] section of the config (or set LX_PROFILE)"
+    )]
+    pub profile: Option<String>,
+
+    #[arg(
+        long = "no-config",
+        conflicts_with_all = ["config", "check_config", "init_config", "config_path"],
+        help = "Ignore the config file and use built-in defaults"
+    )]
+    pub no_config: bool,
+
+    #[arg(
+        long = "check-config",
+        help = "Validate the config file and exit, failing if it has problems"
+    )]
+    pub check_config: bool,
+
+    #[arg(
+        long = "init-config",
+        conflicts_with_all = ["check_config", "show_config", "config_path"],
+        help = "Write a commented default config file to the config path and exit"
+    )]
+    pub init_config: bool,
+
+    #[arg(
+        long,
+        requires = "init_config",
+        help = "Let --init-config overwrite an existing config file"
+    )]
+    pub force: bool,
+
+    #[arg(
+        long = "show-config",
+        conflicts_with_all = ["check_config", "config_path"],
+        help = "Print the effective configuration as TOML and exit"
+    )]
+    pub show_config: bool,
+
+    #[arg(
+        long = "config-path",
+        conflicts_with = "check_config",
+        help = "Print the config files that would be loaded and exit"
+    )]
+    pub config_path: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Ndjson,
 }
 
 impl Args {
@@ -101,6 +250,85 @@ impl Args {
         Self::try_parse_args_from(std::env::args_os()).unwrap_or_else(|error| error.exit())
     }
 
+    /// Parses the command line with a profile's default flags placed before
+    /// it, so flags given on the command line override them. Default flags
+    /// that conflict with one on the command line, such as `-l` with `-1`, are
+    /// dropped.
+    pub fn parse_args_with_defaults(defaults: &[String]) -> Self {
+        Self::try_parse_args_with_defaults(defaults, std::env::args_os())
+            .unwrap_or_else(|error| error.exit())
+    }
+
+    /// Like `parse_args_with_defaults`, for the command line in `arguments`.
+    pub fn try_parse_args_with_defaults<I, T>(
+        defaults: &[String],
+        arguments: I,
+    ) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let mut arguments = arguments.into_iter().map(Into::into);
+        let program = arguments.next().unwrap_or_else(|| "lx".into());
+        let arguments: Vec<OsString> = arguments.collect();
+        let command = Self::command();
+        let profile = command.clone().try_get_matches_from(normalize_sort_order(
+            std::iter::once(program.clone()).chain(defaults.iter().map(OsString::from)),
+        ))?;
+
+        // A command line that only parses with the defaults, such as
+        // `--sort-order` for a profile's `--sort`, takes them all
+        let Ok(given) = command.clone().try_get_matches_from(normalize_sort_order(
+            std::iter::once(program.clone()).chain(arguments.iter().cloned()),
+        )) else {
+            return Self::try_parse_args_from(
+                std::iter::once(program)
+                    .chain(defaults.iter().map(OsString::from))
+                    .chain(arguments),
+            );
+        };
+
+        let given_ids: Vec<&str> = given
+            .ids()
+            .map(|id| id.as_str())
+            .filter(|id| given.value_source(id) == Some(ValueSource::CommandLine))
+            .collect();
+        let mut kept: Vec<&Arg> = command
+            .get_arguments()
+            .filter(|arg| {
+                let id = arg.get_id().as_str();
+                profile.value_source(id) == Some(ValueSource::CommandLine)
+                    && !given_ids
+                        .iter()
+                        .any(|given_id| conflicts(&command, arg, given_id))
+            })
+            .collect();
+        kept.sort_by_key(|arg| profile.index_of(arg.get_id().as_str()));
+
+        let mut targets = Vec::new();
+        let mut flags = Vec::new();
+        for arg in kept {
+            if arg.is_positional() {
+                // The command line's own targets replace the profile's
+                if !given_ids.contains(&arg.get_id().as_str()) {
+                    targets.extend(raw_values(&profile, arg).map(OsStr::to_os_string));
+                }
+            } else {
+                flags.extend(profile_flags(&profile, arg));
+            }
+        }
+        if !targets.is_empty() && !arguments.iter().any(|argument| argument == "--") {
+            targets.insert(0, "--".into());
+        }
+
+        Self::try_parse_args_from(
+            std::iter::once(program)
+                .chain(flags)
+                .chain(arguments)
+                .chain(targets),
+        )
+    }
+
     pub fn try_parse_args_from<I, T>(arguments: I) -> Result<Self, clap::Error>
     where
         I: IntoIterator<Item = T>,
@@ -109,11 +337,103 @@ impl Args {
         <Self as Parser>::try_parse_from(normalize_sort_order(arguments))
     }
 
+    /// The first config flag given, such as `--config`. lx reads the config
+    /// and acts on these before it applies a profile, so profiles cannot set them.
+    pub fn config_flag(&self) -> Option<&'static str> {
+        [
+            (self.config.is_some(), "--config"),
+            (self.no_config, "--no-config"),
+            (self.profile.is_some(), "--profile"),
+            (self.check_config, "--check-config"),
+            (self.init_config, "--init-config"),
+            (self.force, "--force"),
+            (self.show_config, "--show-config"),
+            (self.config_path, "--config-path"),
+        ]
+        .into_iter()
+        .find_map(|(is_given, flag)| is_given.then_some(flag))
+    }
+
     pub fn sort_options(&self, configured_default: SortOptions) -> SortOptions {
         self.sort
             .map(|field| SortOptions::new(Some(field), self.sort_order.unwrap_or_default()))
             .unwrap_or(configured_default)
     }
+
+    /// The formatter named by `--format` or implied by a layout flag such as
+    /// `-l`, or `None` to use the configured one.
+    pub fn format_name(&self) -> Option<&str> {
+        if let Some(format) = &self.format {
+            Some(format)
+        } else if self.long {
+            Some("long")
+        } else if self.one_per_line {
+            Some("one-per-line")
+        } else if self.compact {
+            Some("compact")
+        } else if self.grid {
+            Some("short")
+        } else if self.recursive {
+            Some("tree")
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether `arg` cannot be given together with the argument `id`, directly or
+/// as part of a group that allows only one of its arguments.
+fn conflicts(command: &Command, arg: &Arg, id: &str) -> bool {
+    let Some(other) = command.get_arguments().find(|other| other.get_id() == id) else {
+        return false;
+    };
+    if arg.get_id() == other.get_id() {
+        return false;
+    }
+
+    let declared = |first: &Arg, second: &Arg| {
+        command
+            .get_arg_conflicts_with(first)
+            .iter()
+            .any(|conflict| conflict.get_id() == second.get_id())
+    };
+    let grouped = command.get_groups().any(|group| {
+        !group.clone().is_multiple()
+            && group.get_args().any(|member| member == arg.get_id())
+            && group.get_args().any(|member| member == other.get_id())
+    });
+    declared(arg, other) || declared(other, arg) || grouped
+}
+
+fn raw_values<'a>(matches: &'a ArgMatches, arg: &Arg) -> impl Iterator<Item = &'a OsStr> {
+    matches.get_raw(arg.get_id().as_str()).into_iter().flatten()
+}
+
+/// The flags that give `arg` the values it was parsed with, one per value,
+/// such as `--sort=size`.
+fn profile_flags(matches: &ArgMatches, arg: &Arg) -> Vec<OsString> {
+    let name = match (arg.get_long(), arg.get_short()) {
+        (Some(long), _) => format!("--{long}"),
+        (None, Some(short)) => format!("-{short}"),
+        (None, None) => return Vec::new(),
+    };
+    if !arg.get_action().takes_values() {
+        return vec![name.into()];
+    }
+
+    let separator = if arg.get_long().is_some() { "=" } else { "" };
+    let flags: Vec<OsString> = raw_values(matches, arg)
+        .map(|value| {
+            let mut flag = OsString::from(format!("{name}{separator}"));
+            flag.push(value);
+            flag
+        })
+        .collect();
+    if flags.is_empty() {
+        vec![name.into()]
+    } else {
+        flags
+    }
 }
 
 fn normalize_sort_order<I, T>(arguments: I) -> Vec<OsString>
@@ -198,7 +518,7 @@ fn is_short_flag_group_ending_in_sort(argument: &str) -> bool {
         && flags.ends_with('s')
         && flags[..flags.len() - 1]
             .chars()
-            .all(|flag| matches!(flag, 'a' | 'l' | 'f' | 'd' | '1' | 'c' | 'r'))
+            .all(|flag| matches!(flag, 'a' | 'l' | 'f' | 'd' | '1' | 'c' | 'C' | 'r'))
 }
 
 fn is_sort_order(argument: &OsString) -> bool {
@@ -207,9 +527,11 @@ fn is_sort_order(argument: &OsString) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::Args;
+    use super::{Args, OutputFormat};
     use crate::sort::{SortField, SortOrder};
+    use crate::terminal::When;
     use clap::Parser;
+    use std::path::PathBuf;
 
     #[test]
     fn handles_short_flag_groups_and_conflicts() {
@@ -218,7 +540,9 @@ mod tests {
         assert!(args.show_hidden);
         assert!(args.long);
         assert!(args.recursive);
-        assert_eq!(args.target, ".");
+        assert_eq!(args.targets, ["."]);
+        let targets = Args::try_parse_from(["lx", "-l", "src", "tests"]).expect("parse targets");
+        assert_eq!(targets.targets, ["src", "tests"]);
         assert!(
             Args::try_parse_from(["lx", "-c"])
                 .expect("parse -c")
@@ -227,6 +551,8 @@ mod tests {
         assert!(Args::try_parse_from(["lx", "-l1"]).is_err());
         assert!(Args::try_parse_from(["lx", "-1r"]).is_err());
         assert!(Args::try_parse_from(["lx", "-cl"]).is_err());
+        assert!(Args::try_parse_from(["lx", "-aC"]).expect("parse -aC").grid);
+        assert!(Args::try_parse_from(["lx", "-Cc"]).is_err());
         let filters = Args::try_parse_from(["lx", "-fd"]).expect("parse -fd");
         assert!(filters.files && filters.directories);
         assert!(Args::try_parse_from(["lx", "-rf"]).is_err());
@@ -238,5 +564,91 @@ mod tests {
         assert_eq!(sort.sort, Some(SortField::Size));
         assert_eq!(sort.sort_order, Some(SortOrder::Desc));
         assert!(Args::try_parse_from(["lx", "--sort-order", "desc"]).is_err());
+        let color =
+            Args::try_parse_from(["lx", "--color", "--icons=never"]).expect("parse --color");
+        assert_eq!(color.color, Some(When::Always));
+        assert_eq!(color.icons, Some(When::Never));
+        let json =
+            Args::try_parse_from(["lx", "-r", "--output", "ndjson"]).expect("parse --output");
+        assert_eq!(json.output, OutputFormat::Ndjson);
+        assert!(Args::try_parse_from(["lx", "-l", "--output=json"]).is_err());
+        let format = |arguments: &[&str]| {
+            Args::try_parse_from(arguments)
+                .map(|args| args.format_name().map(str::to_string))
+                .ok()
+        };
+        assert_eq!(
+            format(&["lx", "--format", "tree"]),
+            Some(Some("tree".into()))
+        );
+        assert_eq!(format(&["lx", "-lr"]), Some(Some("long".into())));
+        assert_eq!(format(&["lx", "-r"]), Some(Some("tree".into())));
+        assert_eq!(format(&["lx", "-a"]), Some(None));
+        assert_eq!(format(&["lx", "--format", "long", "-1"]), None);
+
+        let check = Args::try_parse_from(["lx", "--check-config"]).expect("parse --check-config");
+        assert!(check.check_config);
+
+        let config =
+            Args::try_parse_from(["lx", "--config", "lx.toml", "src"]).expect("parse --config");
+        assert_eq!(config.config, Some(PathBuf::from("lx.toml")));
+        assert_eq!(config.targets, vec!["src"]);
+        assert!(Args::try_parse_from(["lx", "--no-config", "--config", "lx.toml"]).is_err());
+
+        assert!(Args::try_parse_from(["lx", "--force"]).is_err());
+        assert!(Args::try_parse_from(["lx", "--init-config", "--force"]).is_ok());
+        assert!(Args::try_parse_from(["lx", "--show-config", "--config-path"]).is_err());
+
+        // Profile flags are parsed ahead of the command line, so repeats must be allowed
+        let profile = Args::try_parse_from(["lx", "-l", "--profile", "audit", "-l"])
+            .expect("parse --profile");
+        assert!(profile.long);
+        assert_eq!(profile.profile.as_deref(), Some("audit"));
+
+        let git_ignore = Args::try_parse_from(["lx", "--git-ignore", "--no-git-ignore"])
+            .expect("parse --no-git-ignore");
+        assert!(!git_ignore.git_ignore);
+        assert!(git_ignore.no_git_ignore);
+        assert!(
+            Args::try_parse_from(["lx", "--git", "-l"])
+                .expect("parse --git")
+                .git
+        );
+    }
+
+    #[test]
+    fn profile_defaults_give_way_to_conflicting_flags() {
+        let defaults = |defaults: &[&str], arguments: &[&str]| {
+            let defaults: Vec<String> = defaults.iter().map(|flag| flag.to_string()).collect();
+            Args::try_parse_args_with_defaults(&defaults, arguments.iter().copied())
+        };
+
+        let one_per_line = defaults(&["-la"], &["lx", "-1"]).expect("drop -l for -1");
+        assert!(one_per_line.one_per_line && one_per_line.show_hidden);
+        assert!(!one_per_line.long);
+
+        let files = defaults(&["-r", "-a"], &["lx", "-f"]).expect("drop -r for -f");
+        assert!(files.files && files.show_hidden);
+        assert!(!files.recursive);
+
+        let format = defaults(&["--format", "tree", "--color"], &["lx", "-c", "src"])
+            .expect("drop --format for -c");
+        assert!(format.compact);
+        assert_eq!(format.format, None);
+        assert_eq!(format.color, Some(When::Always));
+        assert_eq!(format.targets, ["src"]);
+
+        let sort = defaults(
+            &["-s", "size", "-x", "a,b"],
+            &["lx", "--sort-order", "desc"],
+        )
+        .expect("--sort-order relies on the profile's --sort");
+        assert_eq!(sort.sort, Some(SortField::Size));
+        assert_eq!(sort.sort_order, Some(SortOrder::Desc));
+        assert_eq!(sort.exclude, ["a", "b"]);
+
+        let targets = defaults(&["-l", "src"], &["lx"]).expect("keep profile targets");
+        assert_eq!(targets.targets, ["src"]);
+        assert!(defaults(&["--bogus"], &["lx"]).is_err());
     }
 }
@@ -1,10 +1,23 @@
+use crate::category::FileCategory;
+use crate::cli::Args;
+use crate::error::LxError;
+use crate::filter::matches_pattern;
+use crate::git::GitChange;
+use crate::ls_colors::LsColors;
+use crate::report::error_message;
 use crate::sort::{SortField, SortOptions, SortOrder};
-use colored::Color;
-use serde::Deserialize;
+use crate::style::Style;
+use crate::terminal::When;
+use crate::validate::validate_config;
+use serde::de::{Error as _, Unexpected};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Deserialize, Clone, Default)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Config {
     #[serde(default)]
     pub colors: ColorConfig,
@@ -12,9 +25,29 @@ pub struct Config {
     pub icons: IconConfig,
     #[serde(default)]
     pub display: DisplayConfig,
+    /// `[profiles.<name>]` overlays, kept as raw tables until one is selected
+    #[serde(default)]
+    pub profiles: BTreeMap<String, toml::Table>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl Config {
+    /// The default command-line flags listed in a profile's `args`.
+    pub fn profile_args(&self, name: &str) -> Vec<String> {
+        self.profiles
+            .get(name)
+            .and_then(|profile| profile.get("args"))
+            .and_then(toml::Value::as_array)
+            .map(|args| {
+                args.iter()
+                    .filter_map(toml::Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ColorConfig {
     #[serde(default = "default_directory_color")]
     pub directory: String,
@@ -24,9 +57,116 @@ pub struct ColorConfig {
     pub regular: String,
     #[serde(default = "default_hidden_color")]
     pub hidden: String,
+    #[serde(default = "default_symlink_color")]
+    pub symlink: String,
+    #[serde(default = "default_broken_symlink_color")]
+    pub broken_symlink: String,
+    #[serde(default = "default_block_device_color")]
+    pub block_device: String,
+    #[serde(default = "default_char_device_color")]
+    pub char_device: String,
+    #[serde(default = "default_fifo_color")]
+    pub fifo: String,
+    #[serde(default = "default_socket_color")]
+    pub socket: String,
+    #[serde(default)]
+    pub permissions: Option<PermissionColorConfig>,
+    #[serde(default)]
+    pub git: GitColorConfig,
+    #[serde(default)]
+    pub extensions: BTreeMap<String, String>,
+    #[serde(default)]
+    pub rules: Vec<ColorRule>,
+    #[serde(default)]
+    pub categories: CategoryColorConfig,
+    #[serde(default)]
+    pub ls_colors: LsColorsMode,
+    /// `LS_COLORS`/`LSCOLORS` as read from the environment by `load_config`
+    #[serde(skip)]
+    pub ls_colors_table: Option<LsColors>,
+}
+
+/// Whether styles from `LS_COLORS` (or BSD `LSCOLORS`) take part in colouring names.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LsColorsMode {
+    /// `LS_COLORS` wins wherever it defines a style for an entry
+    Prefer,
+    /// The lx config wins; `LS_COLORS` only colours regular files lx has no colour for
+    #[default]
+    Fallback,
+    /// `LS_COLORS` is not read at all
+    Ignore,
+}
+
+/// Colours every entry whose name matches `pattern`, a glob using `*` and `?`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ColorRule {
+    pub pattern: String,
+    pub color: String,
+}
+
+/// Colours for regular files whose extension falls into a built-in category.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CategoryColorConfig {
+    #[serde(default = "default_archive_color")]
+    pub archive: String,
+    #[serde(default = "default_image_color")]
+    pub image: String,
+    #[serde(default = "default_audio_color")]
+    pub audio: String,
+    #[serde(default = "default_video_color")]
+    pub video: String,
+    #[serde(default = "default_document_color")]
+    pub document: String,
+    #[serde(default = "default_source_color")]
+    pub source: String,
+    #[serde(default = "default_config_color")]
+    pub config: String,
+    #[serde(default = "default_temporary_color")]
+    pub temporary: String,
+}
+
+/// Per-character colours for the long-format permissions column. Colouring is
+/// only applied when the `[colors.permissions]` table is present.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PermissionColorConfig {
+    #[serde(default = "default_permission_file_type_color")]
+    pub file_type: String,
+    #[serde(default = "default_permission_read_color")]
+    pub read: String,
+    #[serde(default = "default_permission_write_color")]
+    pub write: String,
+    #[serde(default = "default_permission_execute_color")]
+    pub execute: String,
+    #[serde(default = "default_permission_special_color")]
+    pub special: String,
+    #[serde(default = "default_permission_none_color")]
+    pub none: String,
+    #[serde(default = "default_permission_octal_color")]
+    pub octal: String,
+}
+
+/// Colours for git status markers and the long-format `git` column.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GitColorConfig {
+    #[serde(default = "default_git_new_color")]
+    pub new: String,
+    #[serde(default = "default_git_modified_color")]
+    pub modified: String,
+    #[serde(default = "default_git_deleted_color")]
+    pub deleted: String,
+    #[serde(default = "default_git_renamed_color")]
+    pub renamed: String,
+    #[serde(default = "default_git_type_change_color")]
+    pub type_change: String,
+    #[serde(default = "default_git_ignored_color")]
+    pub ignored: String,
+    #[serde(default = "default_git_conflicted_color")]
+    pub conflicted: String,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct IconConfig {
     #[serde(default = "default_directory_icon")]
     pub directory: String,
@@ -34,11 +174,29 @@ pub struct IconConfig {
     pub executable: String,
     #[serde(default = "default_regular_icon")]
     pub regular: String,
+    #[serde(default = "default_symlink_icon")]
+    pub symlink: String,
+    #[serde(default = "default_broken_symlink_icon")]
+    pub broken_symlink: String,
+    #[serde(default = "default_block_device_icon")]
+    pub block_device: String,
+    #[serde(default = "default_char_device_icon")]
+    pub char_device: String,
+    #[serde(default = "default_fifo_icon")]
+    pub fifo: String,
+    #[serde(default = "default_socket_icon")]
+    pub socket: String,
+    #[serde(default = "default_builtin_icons")]
+    pub builtin: bool,
+    #[serde(default)]
+    pub extensions: BTreeMap<String, String>,
+    #[serde(default)]
+    pub filenames: BTreeMap<String, String>,
     #[serde(default)]
     pub colors: IconColorConfig,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct IconColorConfig {
     #[serde(default = "default_directory_icon_color")]
     pub directory: String,
@@ -48,9 +206,21 @@ pub struct IconColorConfig {
     pub regular: String,
     #[serde(default = "default_hidden_icon_color")]
     pub hidden: String,
+    #[serde(default = "default_symlink_icon_color")]
+    pub symlink: String,
+    #[serde(default = "default_broken_symlink_icon_color")]
+    pub broken_symlink: String,
+    #[serde(default = "default_block_device_icon_color")]
+    pub block_device: String,
+    #[serde(default = "default_char_device_icon_color")]
+    pub char_device: String,
+    #[serde(default = "default_fifo_icon_color")]
+    pub fifo: String,
+    #[serde(default = "default_socket_icon_color")]
+    pub socket: String,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DisplayConfig {
     #[serde(default = "default_column_spacing")]
     pub column_spacing: usize,
@@ -72,18 +242,76 @@ pub struct DisplayConfig {
     pub long_format_titles: bool,
     #[serde(default = "default_long_format_title_color")]
     pub long_format_title_color: String,
+    #[serde(default)]
+    pub permissions_style: PermissionsStyle,
+    #[serde(default)]
+    pub layout: ShortLayout,
+    #[serde(default = "default_format")]
+    pub format: String,
+    #[serde(default)]
+    pub color: When,
+    #[serde(default = "default_icons_when")]
+    pub icons: When,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default = "default_local_config")]
+    pub local_config: bool,
+    #[serde(default)]
+    pub git_ignore: bool,
+    #[serde(default)]
+    pub git_status: bool,
+}
+
+/// How the default short format arranges entries when `-c` is not given.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShortLayout {
+    /// One column per file type, wrapped after `max_rows` rows when it is set
+    #[default]
+    Columns,
+    /// As many columns as fit the terminal width
+    Grid,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionsStyle {
+    /// `-rwsr-xr-x`
+    #[default]
+    Symbolic,
+    /// `4755`
+    Octal,
+    /// `-rwsr-xr-x 4755`
+    Both,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TreeConfig {
     #[serde(default = "default_tree_style")]
     pub style: String,
+    /// How many levels recursive listings descend, like `-L`; unlimited when unset
+    #[serde(default, deserialize_with = "deserialize_max_depth")]
+    pub max_depth: Option<usize>,
+}
+
+/// Rejects a depth of 0, which `-L` does not accept either.
+fn deserialize_max_depth<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<usize>, D::Error> {
+    match Option::<usize>::deserialize(deserializer)? {
+        Some(0) => Err(D::Error::invalid_value(
+            Unexpected::Unsigned(0),
+            &"a depth of at least 1",
+        )),
+        max_depth => Ok(max_depth),
+    }
 }
 
 impl Default for TreeConfig {
     fn default() -> Self {
         TreeConfig {
             style: default_tree_style(),
+            max_depth: None,
         }
     }
 }
@@ -94,6 +322,15 @@ impl Default for IconConfig {
             directory: default_directory_icon(),
             executable: default_executable_icon(),
             regular: default_regular_icon(),
+            symlink: default_symlink_icon(),
+            broken_symlink: default_broken_symlink_icon(),
+            block_device: default_block_device_icon(),
+            char_device: default_char_device_icon(),
+            fifo: default_fifo_icon(),
+            socket: default_socket_icon(),
+            builtin: default_builtin_icons(),
+            extensions: BTreeMap::new(),
+            filenames: BTreeMap::new(),
             colors: IconColorConfig::default(),
         }
     }
@@ -106,6 +343,12 @@ impl Default for IconColorConfig {
             executable: default_executable_icon_color(),
             regular: default_regular_icon_color(),
             hidden: default_hidden_icon_color(),
+            symlink: default_symlink_icon_color(),
+            broken_symlink: default_broken_symlink_icon_color(),
+            block_device: default_block_device_icon_color(),
+            char_device: default_char_device_icon_color(),
+            fifo: default_fifo_icon_color(),
+            socket: default_socket_icon_color(),
         }
     }
 }
@@ -117,6 +360,62 @@ impl Default for ColorConfig {
             executable: default_executable_color(),
             regular: default_regular_color(),
             hidden: default_hidden_color(),
+            symlink: default_symlink_color(),
+            broken_symlink: default_broken_symlink_color(),
+            block_device: default_block_device_color(),
+            char_device: default_char_device_color(),
+            fifo: default_fifo_color(),
+            socket: default_socket_color(),
+            permissions: None,
+            git: GitColorConfig::default(),
+            extensions: BTreeMap::new(),
+            rules: Vec::new(),
+            categories: CategoryColorConfig::default(),
+            ls_colors: LsColorsMode::default(),
+            ls_colors_table: None,
+        }
+    }
+}
+
+impl Default for CategoryColorConfig {
+    fn default() -> Self {
+        CategoryColorConfig {
+            archive: default_archive_color(),
+            image: default_image_color(),
+            audio: default_audio_color(),
+            video: default_video_color(),
+            document: default_document_color(),
+            source: default_source_color(),
+            config: default_config_color(),
+            temporary: default_temporary_color(),
+        }
+    }
+}
+
+impl Default for PermissionColorConfig {
+    fn default() -> Self {
+        PermissionColorConfig {
+            file_type: default_permission_file_type_color(),
+            read: default_permission_read_color(),
+            write: default_permission_write_color(),
+            execute: default_permission_execute_color(),
+            special: default_permission_special_color(),
+            none: default_permission_none_color(),
+            octal: default_permission_octal_color(),
+        }
+    }
+}
+
+impl Default for GitColorConfig {
+    fn default() -> Self {
+        GitColorConfig {
+            new: default_git_new_color(),
+            modified: default_git_modified_color(),
+            deleted: default_git_deleted_color(),
+            renamed: default_git_renamed_color(),
+            type_change: default_git_type_change_color(),
+            ignored: default_git_ignored_color(),
+            conflicted: default_git_conflicted_color(),
         }
     }
 }
@@ -134,16 +433,25 @@ impl Default for DisplayConfig {
             long_format_fields: default_long_format_fields(),
             long_format_titles: false,
             long_format_title_color: default_long_format_title_color(),
+            permissions_style: PermissionsStyle::default(),
+            layout: ShortLayout::default(),
+            format: default_format(),
+            color: When::Auto,
+            icons: default_icons_when(),
+            exclude: Vec::new(),
+            local_config: default_local_config(),
+            git_ignore: false,
+            git_status: false,
         }
     }
 }
 
 fn default_directory_color() -> String {
-    "blue".to_string()
+    "bold blue".to_string()
 }
 
 fn default_executable_color() -> String {
-    "red".to_string()
+    "bold red".to_string()
 }
 
 fn default_regular_color() -> String {
@@ -154,6 +462,62 @@ fn default_hidden_color() -> String {
     "bright_black".to_string()
 }
 
+fn default_symlink_color() -> String {
+    "bold cyan".to_string()
+}
+
+fn default_broken_symlink_color() -> String {
+    "bold red".to_string()
+}
+
+fn default_block_device_color() -> String {
+    "bold yellow".to_string()
+}
+
+fn default_char_device_color() -> String {
+    "bold yellow".to_string()
+}
+
+fn default_fifo_color() -> String {
+    "yellow".to_string()
+}
+
+fn default_socket_color() -> String {
+    "bold magenta".to_string()
+}
+
+fn default_archive_color() -> String {
+    "bright_red".to_string()
+}
+
+fn default_image_color() -> String {
+    "magenta".to_string()
+}
+
+fn default_audio_color() -> String {
+    "cyan".to_string()
+}
+
+fn default_video_color() -> String {
+    "bright_magenta".to_string()
+}
+
+fn default_document_color() -> String {
+    "bright_white".to_string()
+}
+
+fn default_source_color() -> String {
+    "bright_green".to_string()
+}
+
+fn default_config_color() -> String {
+    "yellow".to_string()
+}
+
+fn default_temporary_color() -> String {
+    "bright_black".to_string()
+}
+
 fn default_column_spacing() -> usize {
     2
 }
@@ -170,6 +534,43 @@ fn default_datetime_format() -> String {
     "%Y-%m-%d %H:%M:%S".to_string()
 }
 
+fn default_format() -> String {
+    "short".to_string()
+}
+
+fn default_icons_when() -> When {
+    When::Always
+}
+
+fn default_local_config() -> bool {
+    true
+}
+
+/// Fields `long_format_fields` may list.
+pub const LONG_FORMAT_FIELDS: &[&str] = &[
+    "permissions",
+    "nlink",
+    "owner",
+    "group",
+    "size",
+    "modified",
+    "icon",
+    "filename",
+    "git",
+];
+
+/// The commented default config written by `--init-config`.
+pub const DEFAULT_CONFIG: &str = include_str!("../config.example");
+
+/// Per-directory config file merged over the global config.
+const LOCAL_CONFIG_FILE_NAME: &str = ".lx.toml";
+
+/// File names looked for in each config directory, in order of preference.
+const CONFIG_FILE_NAMES: [&str; 2] = ["config", "config.toml"];
+
+/// Values `[display.tree] style` accepts.
+pub const TREE_STYLES: &[&str] = &["ascii", "indent"];
+
 fn default_tree_style() -> String {
     "ascii".to_string()
 }
@@ -191,6 +592,62 @@ fn default_long_format_title_color() -> String {
     "bright_black".to_string()
 }
 
+fn default_permission_file_type_color() -> String {
+    "blue".to_string()
+}
+
+fn default_permission_read_color() -> String {
+    "yellow".to_string()
+}
+
+fn default_permission_write_color() -> String {
+    "red".to_string()
+}
+
+fn default_permission_execute_color() -> String {
+    "green".to_string()
+}
+
+fn default_permission_special_color() -> String {
+    "magenta".to_string()
+}
+
+fn default_permission_none_color() -> String {
+    "bright_black".to_string()
+}
+
+fn default_permission_octal_color() -> String {
+    "cyan".to_string()
+}
+
+fn default_git_new_color() -> String {
+    "green".to_string()
+}
+
+fn default_git_modified_color() -> String {
+    "yellow".to_string()
+}
+
+fn default_git_deleted_color() -> String {
+    "red".to_string()
+}
+
+fn default_git_renamed_color() -> String {
+    "cyan".to_string()
+}
+
+fn default_git_type_change_color() -> String {
+    "magenta".to_string()
+}
+
+fn default_git_ignored_color() -> String {
+    "bright_black".to_string()
+}
+
+fn default_git_conflicted_color() -> String {
+    "bold red".to_string()
+}
+
 fn default_directory_icon() -> String {
     "".to_string()
 }
@@ -203,6 +660,34 @@ fn default_regular_icon() -> String {
     "".to_string()
 }
 
+fn default_symlink_icon() -> String {
+    "".to_string()
+}
+
+fn default_broken_symlink_icon() -> String {
+    "".to_string()
+}
+
+fn default_block_device_icon() -> String {
+    "".to_string()
+}
+
+fn default_char_device_icon() -> String {
+    "".to_string()
+}
+
+fn default_fifo_icon() -> String {
+    "".to_string()
+}
+
+fn default_socket_icon() -> String {
+    "".to_string()
+}
+
+fn default_builtin_icons() -> bool {
+    true
+}
+
 fn default_directory_icon_color() -> String {
     "blue".to_string()
 }
@@ -219,40 +704,144 @@ fn default_hidden_icon_color() -> String {
     "bright_black".to_string()
 }
 
+fn default_symlink_icon_color() -> String {
+    "cyan".to_string()
+}
+
+fn default_broken_symlink_icon_color() -> String {
+    "red".to_string()
+}
+
+fn default_block_device_icon_color() -> String {
+    "yellow".to_string()
+}
+
+fn default_char_device_icon_color() -> String {
+    "yellow".to_string()
+}
+
+fn default_fifo_icon_color() -> String {
+    "yellow".to_string()
+}
+
+fn default_socket_icon_color() -> String {
+    "magenta".to_string()
+}
+
 impl ColorConfig {
-    pub fn get_directory_color(&self) -> Color {
+    pub fn get_directory_color(&self) -> Style {
         parse_color(&self.directory)
     }
 
-    pub fn get_executable_color(&self) -> Color {
+    pub fn get_executable_color(&self) -> Style {
         parse_color(&self.executable)
     }
 
-    pub fn get_regular_color(&self) -> Color {
+    pub fn get_regular_color(&self) -> Style {
         parse_color(&self.regular)
     }
 
-    pub fn get_hidden_color(&self) -> Color {
+    pub fn get_hidden_color(&self) -> Style {
         parse_color(&self.hidden)
     }
+
+    pub fn get_symlink_color(&self) -> Style {
+        parse_color(&self.symlink)
+    }
+
+    pub fn get_broken_symlink_color(&self) -> Style {
+        parse_color(&self.broken_symlink)
+    }
+
+    pub fn get_block_device_color(&self) -> Style {
+        parse_color(&self.block_device)
+    }
+
+    pub fn get_char_device_color(&self) -> Style {
+        parse_color(&self.char_device)
+    }
+
+    pub fn get_fifo_color(&self) -> Style {
+        parse_color(&self.fifo)
+    }
+
+    pub fn get_socket_color(&self) -> Style {
+        parse_color(&self.socket)
+    }
+}
+
+impl ColorConfig {
+    /// The colour of the first `[[colors.rules]]` entry whose pattern matches `name`.
+    pub fn get_rule_color(&self, name: &str) -> Option<Style> {
+        self.rules
+            .iter()
+            .find(|rule| matches_pattern(&rule.pattern, name))
+            .map(|rule| parse_color(&rule.color))
+    }
+
+    /// Looks up a `[colors.extensions]` colour for an extension (without the dot).
+    pub fn get_extension_color(&self, extension: &str) -> Option<Style> {
+        find_ignoring_case(&self.extensions, extension).map(|color| parse_color(&color))
+    }
+}
+
+impl CategoryColorConfig {
+    pub fn get_color(&self, category: FileCategory) -> Style {
+        let color = match category {
+            FileCategory::Archive => &self.archive,
+            FileCategory::Image => &self.image,
+            FileCategory::Audio => &self.audio,
+            FileCategory::Video => &self.video,
+            FileCategory::Document => &self.document,
+            FileCategory::Source => &self.source,
+            FileCategory::Config => &self.config,
+            FileCategory::Temporary => &self.temporary,
+        };
+
+        parse_color(color)
+    }
 }
 
 impl IconColorConfig {
-    pub fn get_directory_color(&self) -> Color {
+    pub fn get_directory_color(&self) -> Style {
         parse_color(&self.directory)
     }
 
-    pub fn get_executable_color(&self) -> Color {
+    pub fn get_executable_color(&self) -> Style {
         parse_color(&self.executable)
     }
 
-    pub fn get_regular_color(&self) -> Color {
+    pub fn get_regular_color(&self) -> Style {
         parse_color(&self.regular)
     }
 
-    pub fn get_hidden_color(&self) -> Color {
+    pub fn get_hidden_color(&self) -> Style {
         parse_color(&self.hidden)
     }
+
+    pub fn get_symlink_color(&self) -> Style {
+        parse_color(&self.symlink)
+    }
+
+    pub fn get_broken_symlink_color(&self) -> Style {
+        parse_color(&self.broken_symlink)
+    }
+
+    pub fn get_block_device_color(&self) -> Style {
+        parse_color(&self.block_device)
+    }
+
+    pub fn get_char_device_color(&self) -> Style {
+        parse_color(&self.char_device)
+    }
+
+    pub fn get_fifo_color(&self) -> Style {
+        parse_color(&self.fifo)
+    }
+
+    pub fn get_socket_color(&self) -> Style {
+        parse_color(&self.socket)
+    }
 }
 
 impl IconConfig {
@@ -267,63 +856,385 @@ impl IconConfig {
     pub fn get_regular_icon(&self) -> String {
         self.regular.clone()
     }
+
+    pub fn get_symlink_icon(&self) -> String {
+        self.symlink.clone()
+    }
+
+    pub fn get_broken_symlink_icon(&self) -> String {
+        self.broken_symlink.clone()
+    }
+
+    pub fn get_block_device_icon(&self) -> String {
+        self.block_device.clone()
+    }
+
+    pub fn get_char_device_icon(&self) -> String {
+        self.char_device.clone()
+    }
+
+    pub fn get_fifo_icon(&self) -> String {
+        self.fifo.clone()
+    }
+
+    pub fn get_socket_icon(&self) -> String {
+        self.socket.clone()
+    }
+
+    /// Removes every icon, leaving listings with file names only.
+    pub fn clear(&mut self) {
+        for icon in [
+            &mut self.directory,
+            &mut self.executable,
+            &mut self.regular,
+            &mut self.symlink,
+            &mut self.broken_symlink,
+            &mut self.block_device,
+            &mut self.char_device,
+            &mut self.fifo,
+            &mut self.socket,
+        ] {
+            icon.clear();
+        }
+        self.builtin = false;
+        self.extensions.clear();
+        self.filenames.clear();
+    }
+
+    /// Looks up a user-configured icon for an exact file or directory name.
+    pub fn get_filename_icon(&self, name: &str) -> Option<String> {
+        find_ignoring_case(&self.filenames, name)
+    }
+
+    /// Looks up a user-configured icon for a file extension (without the dot).
+    pub fn get_extension_icon(&self, extension: &str) -> Option<String> {
+        find_ignoring_case(&self.extensions, extension)
+    }
+
+    /// Whether any file type or name has a non-empty icon configured.
+    pub fn has_icons(&self) -> bool {
+        [
+            &self.directory,
+            &self.executable,
+            &self.regular,
+            &self.symlink,
+            &self.broken_symlink,
+            &self.block_device,
+            &self.char_device,
+            &self.fifo,
+            &self.socket,
+        ]
+        .iter()
+        .any(|icon| !icon.is_empty())
+            || !self.extensions.is_empty()
+            || !self.filenames.is_empty()
+    }
+}
+
+impl PermissionColorConfig {
+    /// Picks the colour for one character of a rendered permission string.
+    pub fn get_color(&self, index: usize, character: char) -> Style {
+        let color = match character {
+            _ if index == 0 && !character.is_ascii_digit() => &self.file_type,
+            'r' => &self.read,
+            'w' => &self.write,
+            'x' => &self.execute,
+            's' | 'S' | 't' | 'T' => &self.special,
+            '0'..='7' => &self.octal,
+            _ => &self.none,
+        };
+
+        parse_color(color)
+    }
+}
+
+impl GitColorConfig {
+    pub fn get_color(&self, change: GitChange) -> Style {
+        let color = match change {
+            GitChange::New => &self.new,
+            GitChange::Modified => &self.modified,
+            GitChange::Deleted => &self.deleted,
+            GitChange::Renamed => &self.renamed,
+            GitChange::TypeChange => &self.type_change,
+            GitChange::Ignored => &self.ignored,
+            GitChange::Conflicted => &self.conflicted,
+        };
+
+        parse_color(color)
+    }
 }
 
 impl DisplayConfig {
-    pub fn get_long_format_title_color(&self) -> Color {
+    pub fn get_long_format_title_color(&self) -> Style {
         parse_color(&self.long_format_title_color)
     }
 
+    /// Whether entries need their git status: for markers, or for the long
+    /// format's `git` column.
+    pub fn shows_git_status(&self, long: bool) -> bool {
+        self.git_status || (long && self.long_format_fields.iter().any(|field| field == "git"))
+    }
+
     pub fn default_sort(&self) -> SortOptions {
         SortOptions::new(self.sort_field, self.sort_order)
     }
 }
 
-fn parse_color(color_str: &str) -> Color {
-    match color_str.to_lowercase().as_str() {
-        "black" => Color::Black,
-        "red" => Color::Red,
-        "green" => Color::Green,
-        "yellow" => Color::Yellow,
-        "blue" => Color::Blue,
-        "magenta" => Color::Magenta,
-        "cyan" => Color::Cyan,
-        "white" => Color::White,
-        "bright_black" => Color::BrightBlack,
-        "bright_red" => Color::BrightRed,
-        "bright_green" => Color::BrightGreen,
-        "bright_yellow" => Color::BrightYellow,
-        "bright_blue" => Color::BrightBlue,
-        "bright_magenta" => Color::BrightMagenta,
-        "bright_cyan" => Color::BrightCyan,
-        "bright_white" => Color::BrightWhite,
-        _ => Color::White, // Default fallback
+fn find_ignoring_case(table: &BTreeMap<String, String>, key: &str) -> Option<String> {
+    table
+        .iter()
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value.clone())
+}
+
+/// Parses a colour or style. `load_config` refuses styles lx does not
+/// understand, so only a config built some other way can fall back to white.
+fn parse_color(color_str: &str) -> Style {
+    Style::parse(color_str).unwrap_or_else(|_| Style::parse("white").unwrap_or_default())
+}
+
+/// Loads the global config file at `config_path`, or just the built-in
+/// defaults when it is `None` as with `--no-config`, and merges any
+/// `.lx.toml` files from `directory` and its ancestors over it field by field.
+/// The selected profile, if any, is merged over the result the same way.
+pub fn load_config(
+    config_path: Option<&Path>,
+    directory: &Path,
+    profile: Option<&str>,
+) -> Result<Config, LxError> {
+    let mut merged = toml::Table::new();
+    let mut config = Config::default();
+    for path in config_files(config_path, directory) {
+        if let Some(table) = read_config_table(&path)? {
+            merge_tables(&mut merged, table);
+            // Each file is valid alone, so a failure here is this file's
+            // values clashing with those merged before it
+            config = merged
+                .clone()
+                .try_into()
+                .map_err(|error: toml::de::Error| {
+                    let message = format!(
+                        "invalid config file '{}' after merging: {}",
+                        path.display(),
+                        error.message()
+                    );
+                    LxError::config(Some(path.clone()), message)
+                })?;
+        }
+    }
+
+    if let Some(name) = profile {
+        let mut overlay = config
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| LxError::config(None, format!("unknown profile '{name}'")))?;
+        let args = overlay.remove("args");
+        let is_flag_list = |args: &toml::Value| {
+            args.as_array()
+                .is_some_and(|args| args.iter().all(toml::Value::is_str))
+        };
+        if args.is_some_and(|args| !is_flag_list(&args)) {
+            let message = format!("invalid profile '{name}': args must be an array of strings");
+            return Err(LxError::config(None, message));
+        }
+        let flags = config.profile_args(name);
+        let parsed = Args::try_parse_args_from(std::iter::once("lx".to_string()).chain(flags));
+        if let Some(flag) = parsed.ok().and_then(|args| args.config_flag()) {
+            let message =
+                format!("invalid profile '{name}': `{flag}` is not allowed in profile args");
+            return Err(LxError::config(None, message));
+        }
+        merge_tables(&mut merged, overlay);
+        config = merged.try_into().map_err(|error: toml::de::Error| {
+            LxError::config(
+                None,
+                format!("invalid profile '{name}': {}", error.message()),
+            )
+        })?;
+    }
+
+    if config.colors.ls_colors != LsColorsMode::Ignore {
+        config.colors.ls_colors_table = LsColors::from_env();
     }
+    Ok(config)
 }
 
-pub fn load_config() -> Config {
-    let config_path = get_config_path();
+/// Every config file contributing to the effective config, lowest precedence
+/// first: the global file, then `.lx.toml` files from the outermost ancestor
+/// of `directory` down to `directory` itself.
+pub fn config_files(config_path: Option<&Path>, directory: &Path) -> Vec<PathBuf> {
+    let Some(config_path) = config_path else {
+        return Vec::new();
+    };
 
-    if !config_path.exists() {
-        return Config::default();
+    let mut files = Vec::new();
+    if config_path.exists() {
+        files.push(config_path.to_path_buf());
+        if !local_config_enabled(config_path) {
+            return files;
+        }
     }
 
-    match fs::read_to_string(&config_path) {
-        Ok(contents) => match toml::from_str(&contents) {
-            Ok(config) => config,
-            Err(e) => {
-                eprintln!("Warning: Failed to parse config file: {}", e);
-                Config::default()
+    let directory = directory
+        .canonicalize()
+        .unwrap_or_else(|_| directory.to_path_buf());
+    let mut local_files: Vec<PathBuf> = directory
+        .ancestors()
+        .map(|ancestor| ancestor.join(LOCAL_CONFIG_FILE_NAME))
+        .filter(|path| path.is_file() && path != config_path)
+        .collect();
+    local_files.reverse();
+
+    files.extend(local_files);
+    files
+}
+
+/// Whether the global config leaves `.lx.toml` lookup on.
+fn local_config_enabled(config_path: &Path) -> bool {
+    fs::read_to_string(config_path)
+        .ok()
+        .and_then(|contents| toml::from_str::<Config>(&contents).ok())
+        .is_none_or(|config| config.display.local_config)
+}
+
+/// Reads one config file as a TOML table, warning about any problems in it. A
+/// file that does not deserialize as a `Config`, or has a style lx cannot
+/// show, is an error.
+fn read_config_table(config_path: &Path) -> Result<Option<toml::Table>, LxError> {
+    let contents = match fs::read_to_string(config_path) {
+        Ok(contents) => contents,
+        Err(error) => {
+            eprintln!(
+                "lx: cannot read config file '{}': {}",
+                config_path.display(),
+                error_message(&error)
+            );
+            return Ok(None);
+        }
+    };
+
+    let issues = validate_config(config_path, &contents);
+    for issue in &issues {
+        eprintln!("lx: {issue}");
+    }
+    if issues.iter().any(|issue| issue.is_error) {
+        let message = format!("invalid styles in config file '{}'", config_path.display());
+        return Err(LxError::config(Some(config_path.to_path_buf()), message));
+    }
+
+    if let Err(error) = toml::from_str::<Config>(&contents) {
+        let message = format!(
+            "invalid config file '{}': {}",
+            config_path.display(),
+            error.message()
+        );
+        return Err(LxError::config(Some(config_path.to_path_buf()), message));
+    }
+    Ok(contents.parse().ok())
+}
+
+/// Overlays `overlay` onto `base`, merging nested tables key by key and
+/// replacing every other value, arrays included.
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_tables(base_table, overlay_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
             }
-        },
-        Err(_) => Config::default(),
+        }
     }
 }
 
-fn get_config_path() -> PathBuf {
-    let mut path = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-    path.push(".config");
-    path.push("lx");
-    path.push("config");
-    path
+/// Validates config files for `--check-config`, printing every problem to
+/// stderr and each valid file to `out`, and returning whether they are all valid.
+pub fn check_config(out: &mut dyn Write, config_files: &[PathBuf]) -> io::Result<bool> {
+    if config_files.is_empty() {
+        writeln!(out, "No config file found, using built-in defaults")?;
+        return Ok(true);
+    }
+
+    let mut valid = true;
+    for config_path in config_files {
+        let contents = match fs::read_to_string(config_path) {
+            Ok(contents) => contents,
+            Err(error) => {
+                eprintln!("{}: {error}", config_path.display());
+                valid = false;
+                continue;
+            }
+        };
+
+        let issues = validate_config(config_path, &contents);
+        for issue in &issues {
+            eprintln!("{issue}");
+        }
+
+        if issues.is_empty() {
+            writeln!(out, "{}: ok", config_path.display())?;
+        }
+        valid &= issues.is_empty();
+    }
+
+    Ok(valid)
+}
+
+/// Writes the commented default config to `config_path`, creating its
+/// directory. An existing file is only replaced when `force` is set.
+pub fn init_config(config_path: &Path, force: bool) -> io::Result<()> {
+    if config_path.exists() && !force {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "file exists (use --force to overwrite it)",
+        ));
+    }
+
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(config_path, DEFAULT_CONFIG)
+}
+
+/// The config file named by `--config` or, failing that, `LX_CONFIG`.
+pub fn explicit_config_path(flag: Option<&Path>) -> Option<PathBuf> {
+    flag.map(Path::to_path_buf).or_else(|| {
+        env::var_os("LX_CONFIG")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+    })
+}
+
+/// Resolves the config file: `--config`, then `LX_CONFIG`, then the first of
+/// `lx/config` and `lx/config.toml` found under `$XDG_CONFIG_HOME` or
+/// `~/.config`. When none exists, this is where a new config file belongs.
+pub fn get_config_path(flag: Option<&Path>) -> PathBuf {
+    if let Some(path) = explicit_config_path(flag) {
+        return path;
+    }
+
+    let directories = config_directories();
+    directories
+        .iter()
+        .flat_map(|directory| CONFIG_FILE_NAMES.map(|name| directory.join(name)))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| directories[0].join(CONFIG_FILE_NAMES[0]))
+}
+
+fn config_directories() -> Vec<PathBuf> {
+    let mut directories = Vec::new();
+
+    // The XDG spec says relative paths are invalid and should be ignored
+    if let Some(config_home) = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+    {
+        directories.push(config_home.join("lx"));
+    }
+
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    directories.push(home.join(".config").join("lx"));
+    directories
 }
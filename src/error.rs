@@ -0,0 +1,134 @@
+/// Errors that stop lx from listing something, each worded as `ls` would and
+/// with its own exit status so scripts can tell them apart.
+use crate::report::error_message;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// What lx was doing with a path when it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Reading a path's metadata
+    Access,
+    /// Reading a directory's entries
+    OpenDirectory,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Access => write!(f, "cannot access"),
+            Operation::OpenDirectory => write!(f, "cannot open directory"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LxError {
+    /// A target that does not exist; exit status 2, as with `ls`
+    NotFound { path: PathBuf },
+    /// A target lx is not allowed to read; exit status 3
+    PermissionDenied { path: PathBuf, operation: Operation },
+    /// Any other problem reading a target; exit status 4
+    Io {
+        path: PathBuf,
+        operation: Operation,
+        source: io::Error,
+    },
+    /// A config file that is missing, invalid or cannot be written, or a
+    /// setting such as a profile or format that does not exist; exit status 5
+    Config {
+        path: Option<PathBuf>,
+        message: String,
+    },
+    /// An exclude pattern that could never match a name; exit status 6
+    InvalidPattern {
+        pattern: String,
+        reason: &'static str,
+    },
+    /// Writing the listing failed; exit status 7, or 141 for a closed pipe as
+    /// if killed by `SIGPIPE`
+    Output(io::Error),
+}
+
+impl LxError {
+    /// Classifies an I/O error met while doing `operation` on `path`.
+    pub fn io(path: impl Into<PathBuf>, operation: Operation, error: io::Error) -> Self {
+        let path = path.into();
+        match error.kind() {
+            io::ErrorKind::NotFound => LxError::NotFound { path },
+            io::ErrorKind::PermissionDenied => LxError::PermissionDenied { path, operation },
+            _ => LxError::Io {
+                path,
+                operation,
+                source: error,
+            },
+        }
+    }
+
+    /// A config problem with `message` already naming the file, if any.
+    pub fn config(path: Option<PathBuf>, message: impl Into<String>) -> Self {
+        LxError::Config {
+            path,
+            message: message.into(),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LxError::NotFound { .. } => 2,
+            LxError::PermissionDenied { .. } => 3,
+            LxError::Io { .. } => 4,
+            LxError::Config { .. } => 5,
+            LxError::InvalidPattern { .. } => 6,
+            LxError::Output(error) if error.kind() == io::ErrorKind::BrokenPipe => {
+                128 + libc::SIGPIPE
+            }
+            LxError::Output(_) => 7,
+        }
+    }
+
+    /// Whether the reader of the output went away, which lx exits on quietly.
+    pub fn is_broken_pipe(&self) -> bool {
+        matches!(self, LxError::Output(error) if error.kind() == io::ErrorKind::BrokenPipe)
+    }
+}
+
+impl fmt::Display for LxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LxError::NotFound { path } => write!(
+                f,
+                "cannot access '{}': No such file or directory",
+                path.display()
+            ),
+            LxError::PermissionDenied { path, operation } => {
+                write!(f, "{operation} '{}': Permission denied", path.display())
+            }
+            LxError::Io {
+                path,
+                operation,
+                source,
+            } => write!(
+                f,
+                "{operation} '{}': {}",
+                path.display(),
+                error_message(source)
+            ),
+            LxError::Config { message, .. } => write!(f, "{message}"),
+            LxError::InvalidPattern { pattern, reason } => {
+                write!(f, "invalid exclude pattern '{pattern}': {reason}")
+            }
+            LxError::Output(error) => write!(f, "write error: {}", error_message(error)),
+        }
+    }
+}
+
+impl std::error::Error for LxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LxError::Io { source, .. } | LxError::Output(source) => Some(source),
+            _ => None,
+        }
+    }
+}
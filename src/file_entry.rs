@@ -1,9 +1,14 @@
 /// Core data structures (FileEntry and FileType) that represent files and its metadata.
-use crate::config::{ColorConfig, IconColorConfig, IconConfig};
-use crate::icon::FileIcon;
+use crate::category::FileCategory;
+use crate::config::{ColorConfig, IconColorConfig, IconConfig, LsColorsMode, PermissionsStyle};
+use crate::git::GitStatus;
+use crate::icon::{self, FileIcon};
+use crate::style::Style;
 use chrono::format::{Item, StrftimeItems};
-use colored::Color;
+use serde::Serialize;
+use serde::ser::{SerializeStruct, Serializer};
 use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 #[derive(Debug, Clone)]
@@ -17,34 +22,136 @@ pub struct FileEntry {
     pub modified: SystemTime,
     pub owner: String,
     pub group: String,
+    pub uid: u32,
+    pub gid: u32,
     pub nlink: u64,
+    pub is_symlink: bool,
+    pub symlink_target: Option<PathBuf>,
+    pub is_broken_symlink: bool,
+    pub special_file: Option<SpecialFile>,
+    pub rdev: u64,
+    /// Set by `GitStatuses` when git status is shown
+    pub git_status: Option<GitStatus>,
 }
 
 impl FileEntry {
     fn get_file_icon(&self) -> FileIcon {
-        if self.is_dir {
-            FileIcon::Directory
-        } else if self.is_executable {
-            FileIcon::Executable
-        } else {
-            FileIcon::RegularFile
+        match self.get_file_type() {
+            FileType::Directory => FileIcon::Directory,
+            FileType::Executable => FileIcon::Executable,
+            FileType::RegularFile => FileIcon::RegularFile,
+            FileType::Symlink if self.is_broken_symlink => FileIcon::BrokenSymlink,
+            FileType::Symlink => FileIcon::Symlink,
+            FileType::BlockDevice => FileIcon::BlockDevice,
+            FileType::CharDevice => FileIcon::CharDevice,
+            FileType::Fifo => FileIcon::Fifo,
+            FileType::Socket => FileIcon::Socket,
         }
     }
 
+    /// Picks an icon by name first: user `[icons.filenames]`, then built-in names,
+    /// then user `[icons.extensions]`, then built-in extensions, and finally the
+    /// file type icon. Built-in icons are skipped when the type icon is empty, so
+    /// blanking the type icons still hides icons entirely.
     pub fn get_icon_custom(&self, config: &IconConfig) -> String {
-        self.get_file_icon().as_str_custom(config)
+        let file_icon = self.get_file_icon();
+        let type_icon = file_icon.as_str_custom(config);
+        if !matches!(
+            file_icon,
+            FileIcon::Directory | FileIcon::Executable | FileIcon::RegularFile
+        ) {
+            return type_icon;
+        }
+
+        let name = self.file_name().to_lowercase();
+        let use_builtin = config.builtin && !type_icon.is_empty();
+
+        if let Some(icon) = config.get_filename_icon(&name) {
+            return icon;
+        }
+        if self.is_dir {
+            return icon::builtin_directory_icon(&name)
+                .filter(|_| use_builtin)
+                .map_or(type_icon, str::to_string);
+        }
+        if let Some(icon) = icon::builtin_filename_icon(&name).filter(|_| use_builtin) {
+            return icon.to_string();
+        }
+
+        // Try compound extensions such as `tar.gz` before their final component
+        for extension in extensions(&name) {
+            if let Some(icon) = config.get_extension_icon(extension) {
+                return icon;
+            }
+            if let Some(icon) = icon::builtin_extension_icon(extension).filter(|_| use_builtin) {
+                return icon.to_string();
+            }
+        }
+
+        type_icon
     }
 
-    pub fn get_color(&self, config: &ColorConfig) -> Color {
-        if self.is_hidden {
-            return config.get_hidden_color();
+    /// The final path component, as entries named on the command line keep their full path.
+    fn file_name(&self) -> String {
+        Path::new(&self.path)
+            .file_name()
+            .unwrap_or(&self.path)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Picks a name style. With `ls_colors = "prefer"` an `LS_COLORS` style wins
+    /// outright; otherwise `[[colors.rules]]` globs apply to every entry first,
+    /// then hidden entries are dimmed, and regular files are coloured by
+    /// `[colors.extensions]`, their built-in category and then `LS_COLORS`.
+    pub fn get_color(&self, config: &ColorConfig) -> Style {
+        let name = self.file_name();
+        let ls_style = config
+            .ls_colors_table
+            .as_ref()
+            .and_then(|table| table.style_for(self, &name));
+
+        if let (LsColorsMode::Prefer, Some(style)) = (config.ls_colors, &ls_style) {
+            return style.clone();
+        }
+
+        if let Some(color) = self.get_config_color(config, &name) {
+            return color;
+        }
+
+        if let (FileType::RegularFile, Some(style)) = (self.get_file_type(), ls_style) {
+            return style;
         }
 
         self.get_file_icon().get_color(config)
     }
 
-    pub fn get_icon_color(&self, config: &IconColorConfig) -> Color {
-        if self.is_hidden {
+    /// The colour lx's own config assigns by name or category, if any.
+    fn get_config_color(&self, config: &ColorConfig, name: &str) -> Option<Style> {
+        if let Some(color) = config.get_rule_color(name) {
+            return Some(color);
+        }
+
+        // A dangling link is worth pointing out even when it is hidden
+        if self.is_hidden && !self.is_broken_symlink {
+            return Some(config.get_hidden_color());
+        }
+
+        if self.get_file_type() != FileType::RegularFile {
+            return None;
+        }
+
+        let name = name.to_lowercase();
+        extensions(&name)
+            .find_map(|ext| config.get_extension_color(ext))
+            .or_else(|| {
+                FileCategory::from_file_name(&name, extensions(&name))
+                    .map(|category| config.categories.get_color(category))
+            })
+    }
+
+    pub fn get_icon_color(&self, config: &IconColorConfig) -> Style {
+        if self.is_hidden && !self.is_broken_symlink {
             return config.get_hidden_color();
         }
 
@@ -52,8 +159,17 @@ impl FileEntry {
     }
 
     pub fn get_file_type(&self) -> FileType {
-        if self.is_dir {
+        if self.is_symlink {
+            FileType::Symlink
+        } else if self.is_dir {
             FileType::Directory
+        } else if let Some(special_file) = self.special_file {
+            match special_file {
+                SpecialFile::BlockDevice => FileType::BlockDevice,
+                SpecialFile::CharDevice => FileType::CharDevice,
+                SpecialFile::Fifo => FileType::Fifo,
+                SpecialFile::Socket => FileType::Socket,
+            }
         } else if self.is_executable {
             FileType::Executable
         } else {
@@ -65,22 +181,22 @@ impl FileEntry {
         let mode = self.mode;
 
         // File type
-        let file_type = if self.is_dir { 'd' } else { '-' };
+        let file_type = self.get_file_type().type_char();
 
         // Owner permissions
         let user_r = if mode & 0o400 != 0 { 'r' } else { '-' };
         let user_w = if mode & 0o200 != 0 { 'w' } else { '-' };
-        let user_x = if mode & 0o100 != 0 { 'x' } else { '-' };
+        let user_x = execute_char(mode & 0o100 != 0, mode & 0o4000 != 0, 's');
 
         // Group permissions
         let group_r = if mode & 0o040 != 0 { 'r' } else { '-' };
         let group_w = if mode & 0o020 != 0 { 'w' } else { '-' };
-        let group_x = if mode & 0o010 != 0 { 'x' } else { '-' };
+        let group_x = execute_char(mode & 0o010 != 0, mode & 0o2000 != 0, 's');
 
         // Other permissions
         let other_r = if mode & 0o004 != 0 { 'r' } else { '-' };
         let other_w = if mode & 0o002 != 0 { 'w' } else { '-' };
-        let other_x = if mode & 0o001 != 0 { 'x' } else { '-' };
+        let other_x = execute_char(mode & 0o001 != 0, mode & 0o1000 != 0, 't');
 
         format!(
             "{}{}{}{}{}{}{}{}{}{}",
@@ -88,7 +204,36 @@ impl FileEntry {
         )
     }
 
+    /// The permission and special bits as four octal digits, e.g. `4755`.
+    pub fn format_octal_permissions(&self) -> String {
+        format!("{:04o}", self.mode & 0o7777)
+    }
+
+    pub fn format_permissions_with_style(&self, style: PermissionsStyle) -> String {
+        match style {
+            PermissionsStyle::Symbolic => self.format_permissions(),
+            PermissionsStyle::Octal => self.format_octal_permissions(),
+            PermissionsStyle::Both => format!(
+                "{} {}",
+                self.format_permissions(),
+                self.format_octal_permissions()
+            ),
+        }
+    }
+
+    /// The ` -> target` suffix shown after symlink names in long listings.
+    pub fn format_symlink_target(&self) -> Option<String> {
+        self.symlink_target
+            .as_ref()
+            .map(|target| format!(" -> {}", target.to_string_lossy()))
+    }
+
     pub fn format_size(&self) -> String {
+        if self.is_device() {
+            let (major, minor) = self.device_numbers();
+            return format!("{major}, {minor}");
+        }
+
         let size = self.size;
         if size < 1024 {
             format!("{}B", size)
@@ -101,6 +246,21 @@ impl FileEntry {
         }
     }
 
+    pub fn is_device(&self) -> bool {
+        matches!(
+            self.special_file,
+            Some(SpecialFile::BlockDevice | SpecialFile::CharDevice)
+        )
+    }
+
+    /// Major and minor device numbers, meaningful only for block and character devices.
+    // `dev_t` and the libc return types vary between platforms, so the casts are not always no-ops
+    #[allow(clippy::unnecessary_cast)]
+    pub fn device_numbers(&self) -> (u32, u32) {
+        let rdev = self.rdev as libc::dev_t;
+        (libc::major(rdev) as u32, libc::minor(rdev) as u32)
+    }
+
     pub fn format_modified_parts(&self, datetime_format: &str) -> Vec<DateTimePart> {
         let datetime: chrono::DateTime<chrono::Local> = self.modified.into();
 
@@ -121,6 +281,76 @@ impl FileEntry {
     }
 }
 
+/// Candidate extensions of a file name, longest first: `a.tar.gz` yields `tar.gz`
+/// then `gz`. A leading dot marks a hidden file rather than an extension.
+fn extensions(name: &str) -> impl Iterator<Item = &str> {
+    name.char_indices()
+        .skip(1)
+        .filter(|&(_, character)| character == '.')
+        .map(move |(index, _)| &name[index + 1..])
+        .filter(|extension| !extension.is_empty())
+}
+
+/// Renders an execute slot the way coreutils does: a set special bit shows as
+/// lowercase when execute is also set, and uppercase when it is not.
+fn execute_char(execute: bool, special: bool, special_char: char) -> char {
+    match (execute, special) {
+        (true, true) => special_char,
+        (false, true) => special_char.to_ascii_uppercase(),
+        (true, false) => 'x',
+        (false, false) => '-',
+    }
+}
+
+/// Machine-readable encoding used by the JSON output modes: raw values rather
+/// than the human-friendly strings shown in long listings.
+impl Serialize for FileEntry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let modified: chrono::DateTime<chrono::Local> = self.modified.into();
+        let mut state = serializer.serialize_struct("FileEntry", 17)?;
+
+        state.serialize_field("name", &self.path.to_string_lossy())?;
+        state.serialize_field("type", &self.get_file_type())?;
+        state.serialize_field("mode", &self.mode)?;
+        state.serialize_field("permissions", &self.format_permissions())?;
+        state.serialize_field("size", &self.size)?;
+        state.serialize_field("modified", &modified.to_rfc3339())?;
+        state.serialize_field("owner", &self.owner)?;
+        state.serialize_field("group", &self.group)?;
+        state.serialize_field("uid", &self.uid)?;
+        state.serialize_field("gid", &self.gid)?;
+        state.serialize_field("nlink", &self.nlink)?;
+        state.serialize_field("hidden", &self.is_hidden)?;
+        state.serialize_field("executable", &self.is_executable)?;
+        state.serialize_field(
+            "symlink_target",
+            &self
+                .symlink_target
+                .as_ref()
+                .map(|target| target.to_string_lossy()),
+        )?;
+        state.serialize_field("broken_symlink", &self.is_broken_symlink)?;
+        state.serialize_field(
+            "device",
+            &self.is_device().then(|| {
+                let (major, minor) = self.device_numbers();
+                Device { major, minor }
+            }),
+        )?;
+        match &self.git_status {
+            Some(git_status) => state.serialize_field("git", git_status)?,
+            None => state.skip_field("git")?,
+        }
+        state.end()
+    }
+}
+
+#[derive(Serialize)]
+struct Device {
+    major: u32,
+    minor: u32,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum DateTimePartAlignment {
     Left,
@@ -133,9 +363,40 @@ pub struct DateTimePart {
     pub alignment: DateTimePartAlignment,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// Files that are neither regular files, directories nor symlinks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialFile {
+    BlockDevice,
+    CharDevice,
+    Fifo,
+    Socket,
+}
+
+/// Entry kinds, declared in the order used to group the default listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum FileType {
     Directory,
     Executable,
     RegularFile,
+    Symlink,
+    BlockDevice,
+    CharDevice,
+    Fifo,
+    Socket,
+}
+
+impl FileType {
+    /// The leading character of a `ls -l` style permission string.
+    pub fn type_char(self) -> char {
+        match self {
+            FileType::Directory => 'd',
+            FileType::Executable | FileType::RegularFile => '-',
+            FileType::Symlink => 'l',
+            FileType::BlockDevice => 'b',
+            FileType::CharDevice => 'c',
+            FileType::Fifo => 'p',
+            FileType::Socket => 's',
+        }
+    }
 }
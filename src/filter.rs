@@ -1,10 +1,14 @@
+use crate::error::LxError;
 use crate::file_entry::FileEntry;
+use crate::ignore::IgnoreRules;
+use std::path::Path;
 
 #[derive(Clone)]
 pub struct EntryFilter {
     files: bool,
     directories: bool,
     excluded_patterns: Vec<String>,
+    git_ignore: bool,
 }
 
 impl EntryFilter {
@@ -13,9 +17,22 @@ impl EntryFilter {
             files,
             directories,
             excluded_patterns,
+            git_ignore: false,
         }
     }
 
+    /// Also hides entries matched by `.gitignore`-style rules.
+    pub fn with_git_ignore(mut self, git_ignore: bool) -> Self {
+        self.git_ignore = git_ignore;
+        self
+    }
+
+    /// The ignore rules for listing `directory`, when `--git-ignore` is on.
+    pub fn ignore_rules(&self, directory: &Path) -> Option<IgnoreRules> {
+        self.git_ignore
+            .then(|| IgnoreRules::for_directory(directory))
+    }
+
     pub fn includes(&self, entry: &FileEntry) -> bool {
         let matches_type = match (self.files, self.directories) {
             (true, false) => !entry.is_dir,
@@ -36,9 +53,47 @@ impl EntryFilter {
             .filter(|entry| self.includes(entry))
             .collect()
     }
+
+    /// Like `apply`, but also honours ignore rules for entries read from `directory`.
+    pub fn apply_in_directory(&self, directory: &Path, entries: Vec<FileEntry>) -> Vec<FileEntry> {
+        let rules = self.ignore_rules(directory);
+        entries
+            .into_iter()
+            .filter(|entry| {
+                self.includes(entry)
+                    && !rules.as_ref().is_some_and(|rules| {
+                        rules.is_ignored(&entry.path.to_string_lossy(), entry.is_dir)
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Rejects exclude patterns that could never match, since they are matched
+/// against entry names alone.
+pub fn check_pattern(pattern: &str) -> Result<(), LxError> {
+    match pattern_problem(pattern) {
+        Some(reason) => Err(LxError::InvalidPattern {
+            pattern: pattern.to_string(),
+            reason,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Why `pattern` could never match a name, if it could not.
+pub(crate) fn pattern_problem(pattern: &str) -> Option<&'static str> {
+    if pattern.is_empty() {
+        Some("the pattern is empty")
+    } else if pattern.contains('/') {
+        Some("patterns match names, not paths")
+    } else {
+        None
+    }
 }
 
-fn matches_pattern(pattern: &str, name: &str) -> bool {
+/// Matches `name` against a glob supporting `*` and `?` wildcards.
+pub(crate) fn matches_pattern(pattern: &str, name: &str) -> bool {
     let pattern: Vec<char> = pattern.chars().collect();
     let name: Vec<char> = name.chars().collect();
     let (mut pattern_index, mut name_index) = (0, 0);
@@ -0,0 +1,201 @@
+use crate::error::LxError;
+use crate::file_entry::FileEntry;
+use crate::formatter::tree::{TreeNode, TreeOptions};
+use crate::reader::{MetadataMode, read_target};
+use crate::sort::{DefaultSort, SortOptions, sort_entries};
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Like [`write_json`], but prints to stdout.
+pub fn format_json(entries: Vec<FileEntry>, sort: SortOptions, ndjson: bool) -> io::Result<()> {
+    write_json(&mut io::stdout().lock(), entries, sort, ndjson)
+}
+
+/// Writes entries as a JSON array, or as one JSON object per line when `ndjson` is set.
+/// Colours and icons never apply to JSON output.
+pub fn write_json(
+    out: &mut dyn Write,
+    mut entries: Vec<FileEntry>,
+    sort: SortOptions,
+    ndjson: bool,
+) -> io::Result<()> {
+    sort_entries(&mut entries, sort);
+
+    if ndjson {
+        for entry in &entries {
+            writeln!(out, "{}", to_json(entry, false)?)?;
+        }
+    } else {
+        writeln!(out, "{}", to_json(&entries, true)?)?;
+    }
+
+    Ok(())
+}
+
+/// Like [`write_json_tree`], but prints to stdout.
+pub fn format_json_tree(
+    path: &Path,
+    options: &TreeOptions,
+    ndjson: bool,
+) -> io::Result<Vec<LxError>> {
+    write_json_tree(&mut io::stdout().lock(), path, options, ndjson)
+}
+
+/// Writes a directory tree as nested JSON objects whose directories carry a
+/// `children` array. With `ndjson`, every entry is written on its own line with
+/// its path relative to the root and its depth instead. Returns the problems
+/// with entries that were left out, for the caller to report.
+pub fn write_json_tree(
+    out: &mut dyn Write,
+    path: &Path,
+    options: &TreeOptions,
+    ndjson: bool,
+) -> io::Result<Vec<LxError>> {
+    if !path.is_dir() {
+        let entries = read_target(path, options.show_hidden, MetadataMode::Full)?;
+        write_json(
+            out,
+            options.filter.apply(entries.value),
+            options.sort,
+            ndjson,
+        )?;
+        return Ok(entries.problems);
+    }
+
+    let tree = options.renderer(DefaultSort::TypeThenName).collect_tree(
+        path,
+        options.show_hidden,
+        MetadataMode::Full,
+    )?;
+    if let Some(root) = &tree.value {
+        write_json_node(out, root, ndjson)?;
+    }
+    Ok(tree.problems)
+}
+
+/// Writes an already collected tree the way [`write_json_tree`] does.
+pub fn write_json_node(out: &mut dyn Write, tree: &TreeNode, ndjson: bool) -> io::Result<()> {
+    if !ndjson {
+        return writeln!(out, "{}", to_json(tree, true)?);
+    }
+
+    match &tree.children {
+        Some(children) => write_ndjson_nodes(out, children, Path::new(""), 1, None),
+        None => Ok(()),
+    }
+}
+
+/// What is listed for one target: its entries, or the file named, or a
+/// directory tree.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Listing {
+    Entries(Vec<FileEntry>),
+    Tree(TreeNode),
+}
+
+/// Writes the listings of several targets as one JSON object keyed by target,
+/// in the order given. With `ndjson`, every entry is written on its own line
+/// as [`write_json`] and [`write_json_node`] do, with a `target` field naming
+/// the target it belongs to. Entries are written in the order given, as each
+/// target may be sorted its own way.
+pub fn write_json_targets(
+    out: &mut dyn Write,
+    listings: Vec<(String, Listing)>,
+    ndjson: bool,
+) -> io::Result<()> {
+    if !ndjson {
+        return writeln!(out, "{}", to_json(&Targets(&listings), true)?);
+    }
+
+    for (target, listing) in &listings {
+        match listing {
+            Listing::Entries(entries) => {
+                for entry in entries {
+                    let line = NdjsonTargetEntry { target, entry };
+                    writeln!(out, "{}", to_json(&line, false)?)?;
+                }
+            }
+            Listing::Tree(tree) => {
+                if let Some(children) = &tree.children {
+                    write_ndjson_nodes(out, children, Path::new(""), 1, Some(target))?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Serializes listings as a map, keeping their order.
+struct Targets<'a>(&'a [(String, Listing)]);
+
+impl Serialize for Targets<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (target, listing) in self.0 {
+            map.serialize_entry(target, listing)?;
+        }
+        map.end()
+    }
+}
+
+#[derive(Serialize)]
+struct NdjsonTargetEntry<'a> {
+    target: &'a str,
+    #[serde(flatten)]
+    entry: &'a FileEntry,
+}
+
+#[derive(Serialize)]
+struct NdjsonTreeEntry<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<&'a str>,
+    path: String,
+    depth: usize,
+    #[serde(flatten)]
+    entry: &'a FileEntry,
+    #[serde(rename = "truncated_entries", skip_serializing_if = "Option::is_none")]
+    truncated: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+fn write_ndjson_nodes(
+    out: &mut dyn Write,
+    nodes: &[TreeNode],
+    parent: &Path,
+    depth: usize,
+    target: Option<&str>,
+) -> io::Result<()> {
+    for node in nodes {
+        let path = parent.join(&node.entry.path);
+        let line = NdjsonTreeEntry {
+            target,
+            path: path.to_string_lossy().into_owned(),
+            depth,
+            entry: &node.entry,
+            truncated: node.truncated,
+            error: node.error.as_deref(),
+        };
+        writeln!(out, "{}", to_json(&line, false)?)?;
+
+        if let Some(children) = &node.children {
+            write_ndjson_nodes(out, children, &path, depth + 1, target)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T, pretty: bool) -> io::Result<String> {
+    let json = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+
+    json.map_err(io::Error::other)
+}
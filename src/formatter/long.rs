@@ -1,23 +1,40 @@
 use crate::config::Config;
-use crate::file_entry::{DateTimePart, DateTimePartAlignment, FileEntry, FileType};
+use crate::file_entry::{DateTimePart, DateTimePartAlignment, FileEntry};
+use crate::formatter::colorize_filename;
 use crate::sort::{SortOptions, sort_entries};
-use colored::Colorize;
+use std::io::{self, Write};
 use unicode_width::UnicodeWidthStr;
 
-pub fn format_long(mut entries: Vec<FileEntry>, config: &Config, sort: SortOptions) {
+/// Like [`write_long`], but prints to stdout.
+pub fn format_long(entries: Vec<FileEntry>, config: &Config, sort: SortOptions) -> io::Result<()> {
+    write_long(&mut io::stdout().lock(), entries, config, sort)
+}
+
+/// Writes entries one per line with the columns in `long_format_fields`, like `ls -l`.
+pub fn write_long(
+    out: &mut dyn Write,
+    mut entries: Vec<FileEntry>,
+    config: &Config,
+    sort: SortOptions,
+) -> io::Result<()> {
     sort_entries(&mut entries, sort);
 
-    print_long_entries(&entries, config, "");
+    write_long_entries(out, &entries, config, "")
 }
 
-pub fn print_long_entries(entries: &[FileEntry], config: &Config, prefix: &str) {
+pub fn write_long_entries(
+    out: &mut dyn Write,
+    entries: &[FileEntry],
+    config: &Config,
+    prefix: &str,
+) -> io::Result<()> {
     if entries.is_empty() {
-        return;
+        return Ok(());
     }
 
     let fields = &config.display.long_format_fields;
     let widths = calculate_column_widths(entries, fields, config);
-    print_long_entries_with_widths(entries, config, prefix, fields, &widths);
+    write_long_entries_with_widths(out, entries, config, prefix, fields, &widths)
 }
 
 pub fn calculate_column_widths(
@@ -54,12 +71,21 @@ pub fn calculate_column_widths(
                 .unwrap_or(0),
             "filename" => entries
                 .iter()
-                .map(|e| UnicodeWidthStr::width(e.path.to_string_lossy().as_ref()))
+                .map(|e| {
+                    UnicodeWidthStr::width(e.path.to_string_lossy().as_ref())
+                        + e.format_symlink_target()
+                            .map(|target| UnicodeWidthStr::width(target.as_str()))
+                            .unwrap_or(0)
+                })
                 .max()
                 .unwrap_or(0),
+            "git" => 2,
             "permissions" => entries
                 .iter()
-                .map(|e| e.format_permissions().len())
+                .map(|e| {
+                    e.format_permissions_with_style(config.display.permissions_style)
+                        .len()
+                })
                 .max()
                 .unwrap_or(0),
             _ => 0,
@@ -78,61 +104,74 @@ pub fn calculate_column_widths(
 pub fn calculate_column_widths_with_filename_prefixes(
     entries: &[FileEntry],
     filename_prefixes: &[String],
+    filename_suffixes: &[String],
     fields: &[String],
     config: &Config,
 ) -> std::collections::HashMap<String, usize> {
     let mut entries_with_prefixes = entries.to_vec();
 
-    for (entry, filename_prefix) in entries_with_prefixes.iter_mut().zip(filename_prefixes) {
-        entry.path = format!("{filename_prefix}{}", entry.path.to_string_lossy()).into();
+    for ((entry, filename_prefix), filename_suffix) in entries_with_prefixes
+        .iter_mut()
+        .zip(filename_prefixes)
+        .zip(filename_suffixes)
+    {
+        let filename = entry.path.to_string_lossy();
+        entry.path = format!("{filename_prefix}{filename}{filename_suffix}").into();
     }
 
     calculate_column_widths(&entries_with_prefixes, fields, config)
 }
 
-pub fn print_long_entries_with_widths(
+pub fn write_long_entries_with_widths(
+    out: &mut dyn Write,
     entries: &[FileEntry],
     config: &Config,
     prefix: &str,
     fields: &[String],
     widths: &std::collections::HashMap<String, usize>,
-) {
+) -> io::Result<()> {
     if config.display.long_format_titles {
-        print_long_titles(config, prefix, fields, widths);
+        write_long_titles(out, config, prefix, fields, widths)?;
     }
-    print_long_entries_with_optional_filename_prefixes(
-        entries, config, prefix, fields, widths, None,
-    );
+    write_long_entries_with_optional_filename_prefixes(
+        out, entries, config, prefix, fields, widths, None,
+    )
 }
 
-pub fn print_long_entries_with_filename_prefixes(
+/// Like `write_long_entries_with_widths`, with text such as tree branches
+/// before each name and notes such as ` … 3 entries` after it.
+pub fn write_long_entries_with_filename_prefixes(
+    out: &mut dyn Write,
     entries: &[FileEntry],
     filename_prefixes: &[String],
+    filename_suffixes: &[String],
     config: &Config,
     fields: &[String],
     widths: &std::collections::HashMap<String, usize>,
-) {
+) -> io::Result<()> {
     if config.display.long_format_titles {
-        print_long_titles(config, "", fields, widths);
+        write_long_titles(out, config, "", fields, widths)?;
     }
-    print_long_entries_with_optional_filename_prefixes(
+    write_long_entries_with_optional_filename_prefixes(
+        out,
         entries,
         config,
         "",
         fields,
         widths,
-        Some(filename_prefixes),
-    );
+        Some((filename_prefixes, filename_suffixes)),
+    )
 }
 
-fn print_long_entries_with_optional_filename_prefixes(
+fn write_long_entries_with_optional_filename_prefixes(
+    out: &mut dyn Write,
     entries: &[FileEntry],
     config: &Config,
     prefix: &str,
     fields: &[String],
     widths: &std::collections::HashMap<String, usize>,
-    filename_prefixes: Option<&[String]>,
-) {
+    filename_affixes: Option<(&[String], &[String])>,
+) -> io::Result<()> {
     let modified_values = format_modified_entries(entries, &config.display.datetime_format);
 
     // Print each entry
@@ -143,12 +182,18 @@ fn print_long_entries_with_optional_filename_prefixes(
             let part = match field.as_str() {
                 "permissions" => {
                     let width = widths.get("permissions").copied().unwrap_or(0);
-                    let permissions = entry.format_permissions();
-                    if idx < fields.len() - 1 {
-                        pad_to_display_width(permissions, width)
+                    let permissions =
+                        entry.format_permissions_with_style(config.display.permissions_style);
+                    let padding = if idx < fields.len() - 1 {
+                        width.saturating_sub(permissions.len())
                     } else {
-                        permissions
-                    }
+                        0
+                    };
+                    format!(
+                        "{}{}",
+                        colorize_permissions(&permissions, config),
+                        " ".repeat(padding)
+                    )
                 }
                 "nlink" => {
                     let width = widths.get("nlink").copied().unwrap_or(0);
@@ -184,34 +229,47 @@ fn print_long_entries_with_optional_filename_prefixes(
                     } else {
                         icon
                     };
-                    format!("{}", padded.color(icon_color))
+                    icon_color.paint(&padded)
                 }
                 "filename" => {
                     let filename_str = entry.path.to_string_lossy().to_string();
+                    let symlink_target = entry.format_symlink_target().unwrap_or_default();
                     let width = widths.get("filename").copied().unwrap_or(0);
-                    let filename_prefix = filename_prefixes
-                        .and_then(|prefixes| prefixes.get(entry_index))
-                        .map(String::as_str)
-                        .unwrap_or("");
+                    let (filename_prefix, filename_suffix) = filename_affixes
+                        .map(|(prefixes, suffixes)| {
+                            (
+                                prefixes.get(entry_index).map_or("", String::as_str),
+                                suffixes.get(entry_index).map_or("", String::as_str),
+                            )
+                        })
+                        .unwrap_or(("", ""));
 
                     let padding = if idx < fields.len() - 1 {
                         width.saturating_sub(
                             UnicodeWidthStr::width(filename_prefix)
-                                + UnicodeWidthStr::width(filename_str.as_str()),
+                                + UnicodeWidthStr::width(filename_str.as_str())
+                                + UnicodeWidthStr::width(symlink_target.as_str())
+                                + UnicodeWidthStr::width(filename_suffix),
                         )
                     } else {
                         0
                     };
-                    let padded = format!("{filename_str}{}", " ".repeat(padding));
 
-                    let filename_colored = match entry.get_file_type() {
-                        FileType::Directory | FileType::Executable => {
-                            padded.color(entry.get_color(&config.colors)).bold()
-                        }
-                        FileType::RegularFile => padded.color(entry.get_color(&config.colors)),
+                    let filename_colored = colorize_filename(entry, &filename_str, config);
+                    let symlink_target = if entry.is_broken_symlink {
+                        config
+                            .colors
+                            .get_broken_symlink_color()
+                            .paint(&symlink_target)
+                    } else {
+                        symlink_target
                     };
-                    format!("{filename_prefix}{filename_colored}")
+                    format!(
+                        "{filename_prefix}{filename_colored}{symlink_target}{filename_suffix}{}",
+                        " ".repeat(padding)
+                    )
                 }
+                "git" => colorize_git_status(entry, config),
                 _ => String::new(),
             };
             output_parts.push(part);
@@ -222,8 +280,47 @@ fn print_long_entries_with_optional_filename_prefixes(
             .map(|part| part.as_str())
             .filter(|part| !part.is_empty())
             .collect();
-        println!("{}{}", prefix, visible_parts.join("  "));
+        writeln!(out, "{}{}", prefix, visible_parts.join("  "))?;
     }
+
+    Ok(())
+}
+
+fn colorize_permissions(permissions: &str, config: &Config) -> String {
+    let Some(colors) = &config.colors.permissions else {
+        return permissions.to_string();
+    };
+
+    permissions
+        .chars()
+        .enumerate()
+        .map(|(index, character)| {
+            if character == ' ' {
+                character.to_string()
+            } else {
+                colors
+                    .get_color(index, character)
+                    .paint(&character.to_string())
+            }
+        })
+        .collect()
+}
+
+/// The staged and unstaged changes as two letters, with `-` for neither.
+fn colorize_git_status(entry: &FileEntry, config: &Config) -> String {
+    let status = entry.git_status.unwrap_or_default();
+
+    [status.staged, status.unstaged]
+        .into_iter()
+        .map(|change| match change {
+            Some(change) => config
+                .colors
+                .git
+                .get_color(change)
+                .paint(&change.marker().to_string()),
+            None => "-".to_string(),
+        })
+        .collect()
 }
 
 fn format_modified_entries(entries: &[FileEntry], datetime_format: &str) -> Vec<String> {
@@ -268,12 +365,13 @@ fn format_datetime_part(part: &DateTimePart, width: usize) -> String {
     }
 }
 
-fn print_long_titles(
+fn write_long_titles(
+    out: &mut dyn Write,
     config: &Config,
     prefix: &str,
     fields: &[String],
     widths: &std::collections::HashMap<String, usize>,
-) {
+) -> io::Result<()> {
     let titles: Vec<String> = fields
         .iter()
         .enumerate()
@@ -286,14 +384,11 @@ fn print_long_titles(
                 title.to_string()
             };
 
-            format!(
-                "{}",
-                padded.color(config.display.get_long_format_title_color())
-            )
+            config.display.get_long_format_title_color().paint(&padded)
         })
         .collect();
 
-    println!("{}{}", prefix, titles.join("  "));
+    writeln!(out, "{}{}", prefix, titles.join("  "))
 }
 
 fn column_title(field: &str) -> &str {
@@ -306,6 +401,7 @@ fn column_title(field: &str) -> &str {
         "modified" => "Modified",
         "icon" => "Icon",
         "filename" => "Name",
+        "git" => "Git",
         _ => field,
     }
 }
@@ -1,10 +1,69 @@
+mod json;
 mod long;
 mod one_per_line;
 mod recursive;
+mod registry;
 mod short;
 mod tree;
 
-pub use long::format_long;
-pub use one_per_line::format_one_per_line;
-pub use recursive::format_recursive;
-pub use short::{format_short, format_short_compact};
+pub use json::{
+    Listing, format_json, format_json_tree, write_json, write_json_node, write_json_targets,
+    write_json_tree,
+};
+pub use long::{format_long, write_long};
+pub use one_per_line::{format_one_per_line, write_one_per_line};
+pub use recursive::{format_recursive, write_recursive};
+pub use registry::{
+    CompactFormatter, Formatter, FormatterRegistry, LongFormatter, OnePerLineFormatter,
+    ShortFormatter, TreeFormatter,
+};
+pub use short::{
+    format_short, format_short_compact, format_short_grid, write_short, write_short_compact,
+    write_short_grid,
+};
+pub use tree::{TreeEntry, TreeNode, TreeOptions, TreeRenderer, flatten_tree};
+
+use crate::config::Config;
+use crate::file_entry::{FileEntry, FileType};
+use std::collections::BTreeMap;
+
+/// Styles a file name for its entry.
+pub(crate) fn colorize_filename(entry: &FileEntry, filename: &str, config: &Config) -> String {
+    entry.get_color(&config.colors).paint(filename)
+}
+
+/// The git status marker shown before an entry when `git_status` is on,
+/// followed by a space. Clean entries get a blank marker to stay aligned.
+pub(crate) fn git_marker(entry: &FileEntry, config: &Config) -> String {
+    if !config.display.git_status {
+        return String::new();
+    }
+
+    match entry.git_status.and_then(|status| status.primary_change()) {
+        Some(change) => format!(
+            "{} ",
+            config
+                .colors
+                .git
+                .get_color(change)
+                .paint(&change.marker().to_string())
+        ),
+        None => "  ".to_string(),
+    }
+}
+
+/// The display width of `git_marker`.
+pub(crate) fn git_marker_width(config: &Config) -> usize {
+    if config.display.git_status { 2 } else { 0 }
+}
+
+/// Splits already-sorted entries into per-type groups, in default type order.
+pub(crate) fn group_by_file_type(entries: Vec<FileEntry>) -> Vec<Vec<FileEntry>> {
+    let mut groups: BTreeMap<FileType, Vec<FileEntry>> = BTreeMap::new();
+
+    for entry in entries {
+        groups.entry(entry.get_file_type()).or_default().push(entry);
+    }
+
+    groups.into_values().collect()
+}
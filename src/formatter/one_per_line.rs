@@ -1,52 +1,56 @@
 use crate::config::Config;
-use crate::file_entry::{FileEntry, FileType};
+use crate::file_entry::FileEntry;
+use crate::formatter::{colorize_filename, git_marker, group_by_file_type};
 use crate::sort::{SortOptions, sort_entries};
-use colored::Colorize;
+use std::io::{self, Write};
 
-pub fn format_one_per_line(mut entries: Vec<FileEntry>, config: &Config, sort: SortOptions) {
+/// Like [`write_one_per_line`], but prints to stdout.
+pub fn format_one_per_line(
+    entries: Vec<FileEntry>,
+    config: &Config,
+    sort: SortOptions,
+) -> io::Result<()> {
+    write_one_per_line(&mut io::stdout().lock(), entries, config, sort)
+}
+
+/// Writes one entry per line, like `ls -1`.
+pub fn write_one_per_line(
+    out: &mut dyn Write,
+    mut entries: Vec<FileEntry>,
+    config: &Config,
+    sort: SortOptions,
+) -> io::Result<()> {
     sort_entries(&mut entries, sort);
 
     if sort.is_custom() {
-        print_entries(&entries, config);
-        return;
+        return write_entries(out, &entries, config);
     }
 
-    let mut directories: Vec<FileEntry> = Vec::new();
-    let mut executables: Vec<FileEntry> = Vec::new();
-    let mut regular_files: Vec<FileEntry> = Vec::new();
-
-    for entry in entries {
-        match entry.get_file_type() {
-            FileType::Directory => directories.push(entry),
-            FileType::Executable => executables.push(entry),
-            FileType::RegularFile => regular_files.push(entry),
-        }
+    for group in group_by_file_type(entries) {
+        write_entries(out, &group, config)?;
     }
 
-    print_entries(&directories, config);
-    print_entries(&executables, config);
-    print_entries(&regular_files, config);
+    Ok(())
 }
 
-fn print_entries(entries: &[FileEntry], config: &Config) {
+fn write_entries(out: &mut dyn Write, entries: &[FileEntry], config: &Config) -> io::Result<()> {
     for entry in entries {
         let filename = entry.path.to_string_lossy();
         let icon = entry.get_icon_custom(&config.icons);
-        let filename_colored = match entry.get_file_type() {
-            FileType::Directory | FileType::Executable => {
-                filename.color(entry.get_color(&config.colors)).bold()
-            }
-            FileType::RegularFile => filename.color(entry.get_color(&config.colors)),
-        };
+        let filename_colored = colorize_filename(entry, &filename, config);
+        let marker = git_marker(entry, config);
 
         if icon.is_empty() {
-            println!("{}", filename_colored);
+            writeln!(out, "{marker}{filename_colored}")?;
         } else {
-            println!(
-                "{} {}",
-                icon.color(entry.get_icon_color(&config.icons.colors)),
+            writeln!(
+                out,
+                "{marker}{} {}",
+                entry.get_icon_color(&config.icons.colors).paint(&icon),
                 filename_colored
-            );
+            )?;
         }
     }
+
+    Ok(())
 }
@@ -1,153 +1,167 @@
 use crate::config::Config;
-use crate::file_entry::{FileEntry, FileType};
-use crate::filter::EntryFilter;
+use crate::error::LxError;
+use crate::file_entry::FileEntry;
 use crate::formatter::long::{
-    calculate_column_widths_with_filename_prefixes, print_long_entries_with_filename_prefixes,
+    calculate_column_widths_with_filename_prefixes, write_long_entries_with_filename_prefixes,
 };
-use crate::formatter::tree::{TreeEntry, TreeRenderer};
-use crate::formatter::{format_long, format_one_per_line};
-use crate::reader::{MetadataMode, read_entry, read_target};
-use crate::sort::{DefaultSort, SortOptions};
-use colored::Colorize;
-use std::io;
+use crate::formatter::tree::{TreeEntry, TreeNode, TreeOptions, flatten_tree};
+use crate::formatter::{Formatter, colorize_filename, git_marker};
+use crate::reader::{MetadataMode, read_target};
+use std::io::{self, Write};
 use std::path::Path;
 
+/// Like [`write_recursive`], but prints to stdout.
 pub fn format_recursive(
     path: &Path,
     config: &Config,
-    show_hidden: bool,
-    use_long_format: bool,
-    filter: &EntryFilter,
-    sort: SortOptions,
-) -> io::Result<()> {
-    if !path.is_dir() {
-        let metadata_mode = if use_long_format || sort.requires_full_metadata() {
-            MetadataMode::Full
-        } else {
-            MetadataMode::Basic
-        };
-        let entries = filter.apply(read_target(path, show_hidden, metadata_mode)?);
-
-        if use_long_format {
-            format_long(entries, config, sort);
-        } else {
-            format_one_per_line(entries, config, sort);
-        }
-        return Ok(());
-    }
+    formatter: &dyn Formatter,
+    options: &TreeOptions,
+) -> io::Result<Vec<LxError>> {
+    write_recursive(&mut io::stdout().lock(), path, config, formatter, options)
+}
 
-    let metadata_mode = if use_long_format || sort.requires_full_metadata() {
+/// Collects the tree below `path` and writes it with `formatter`. A file is
+/// written as a one-entry listing. Returns the problems with entries that
+/// were left out, for the caller to report.
+pub fn write_recursive(
+    out: &mut dyn Write,
+    path: &Path,
+    config: &Config,
+    formatter: &dyn Formatter,
+    options: &TreeOptions,
+) -> io::Result<Vec<LxError>> {
+    let sort = options.sort;
+    let metadata_mode = if formatter.needs_full_metadata() || sort.requires_full_metadata() {
         MetadataMode::Full
     } else {
         MetadataMode::Basic
     };
-    let root = read_entry(path, metadata_mode)?;
-    if !filter.includes(&root) {
-        return Ok(());
+    if !path.is_dir() {
+        let entries = read_target(path, options.show_hidden, metadata_mode)?;
+        formatter.write_entries(out, options.filter.apply(entries.value), config, sort)?;
+        return Ok(entries.problems);
     }
 
-    let uses_icons = !use_long_format
-        && [
-            config.icons.directory.as_str(),
-            config.icons.executable.as_str(),
-            config.icons.regular.as_str(),
-        ]
-        .iter()
-        .any(|icon| !icon.is_empty());
-    let default_sort = if use_long_format {
-        DefaultSort::TypeThenName
-    } else {
-        DefaultSort::Name
-    };
-    let renderer = TreeRenderer::new(
-        &config.display.tree.style,
-        uses_icons,
-        filter,
-        sort,
-        default_sort,
-    );
-    let tree_entries = renderer.collect(path, show_hidden, metadata_mode)?;
-
-    if use_long_format {
-        print_nested_long_tree(&root, &tree_entries, config);
-    } else {
-        print_tree_root(&root, config);
-        print_short_tree(&tree_entries, config);
+    let tree = options.renderer(formatter.tree_sort()).collect_tree(
+        path,
+        options.show_hidden,
+        metadata_mode,
+    )?;
+    if let Some(root) = tree.value {
+        formatter.write_tree(out, root, config)?;
     }
-
-    Ok(())
+    Ok(tree.problems)
 }
 
-fn print_nested_long_tree(root: &FileEntry, tree_entries: &[TreeEntry], config: &Config) {
+/// Writes a tree as a long listing with the branches before each name.
+pub(crate) fn write_long_tree(
+    out: &mut dyn Write,
+    tree: TreeNode,
+    config: &Config,
+) -> io::Result<()> {
+    let children = tree.children.unwrap_or_default();
+    let tree_entries = flatten_tree(children, &config.display.tree.style, false);
+
     let mut display_entries = Vec::with_capacity(tree_entries.len() + 1);
     let mut filename_prefixes = vec![String::new()];
-    display_entries.push(display_entry(root));
-    display_entries.extend(
-        tree_entries
-            .iter()
-            .map(|tree_entry| display_entry(&tree_entry.entry)),
-    );
-    filename_prefixes.extend(
-        tree_entries
-            .iter()
-            .map(|tree_entry| format!("{} ", tree_entry.branch)),
-    );
+    let mut filename_suffixes = vec![String::new()];
+    display_entries.push(display_entry(&tree.entry));
+    for tree_entry in &tree_entries {
+        display_entries.push(display_entry(&tree_entry.entry));
+        filename_prefixes.push(format!("{} ", tree_entry.branch));
+        filename_suffixes.push(tree_entry_suffix(tree_entry));
+    }
 
     let fields = &config.display.long_format_fields;
     let widths = calculate_column_widths_with_filename_prefixes(
         &display_entries,
         &filename_prefixes,
+        &filename_suffixes,
         fields,
         config,
     );
-    print_long_entries_with_filename_prefixes(
+    write_long_entries_with_filename_prefixes(
+        out,
         &display_entries,
         &filename_prefixes,
+        &filename_suffixes,
         config,
         fields,
         &widths,
+    )
+}
+
+/// Writes a tree as its root followed by a line per entry with its branch.
+pub(crate) fn write_branch_tree(
+    out: &mut dyn Write,
+    tree: TreeNode,
+    config: &Config,
+) -> io::Result<()> {
+    write_tree_root(out, &tree.entry, config)?;
+    let children = tree.children.unwrap_or_default();
+    let tree_entries = flatten_tree(
+        children,
+        &config.display.tree.style,
+        config.icons.has_icons(),
     );
+    write_short_tree(out, &tree_entries, config)
 }
 
-fn print_tree_root(root: &FileEntry, config: &Config) {
+fn write_tree_root(out: &mut dyn Write, root: &FileEntry, config: &Config) -> io::Result<()> {
     let root = display_entry(root);
     let filename = root.path.to_string_lossy();
     let icon = root.get_icon_custom(&config.icons);
-    let filename_colored = filename.color(root.get_color(&config.colors)).bold();
+    let filename_colored = root.get_color(&config.colors).paint(&filename);
 
     if icon.is_empty() {
-        println!("{filename_colored}");
+        writeln!(out, "{filename_colored}")
     } else {
-        println!(
+        writeln!(
+            out,
             "{} {filename_colored}",
-            icon.color(root.get_icon_color(&config.icons.colors))
-        );
+            root.get_icon_color(&config.icons.colors).paint(&icon)
+        )
     }
 }
 
-fn print_short_tree(tree_entries: &[TreeEntry], config: &Config) {
+fn write_short_tree(
+    out: &mut dyn Write,
+    tree_entries: &[TreeEntry],
+    config: &Config,
+) -> io::Result<()> {
     for tree_entry in tree_entries {
         let entry = &tree_entry.entry;
         let filename = display_entry(entry).path.to_string_lossy().to_string();
         let icon = entry.get_icon_custom(&config.icons);
-        let filename_colored = match entry.get_file_type() {
-            FileType::Directory | FileType::Executable => {
-                filename.color(entry.get_color(&config.colors)).bold()
-            }
-            FileType::RegularFile => filename.color(entry.get_color(&config.colors)),
-        };
+        let mut filename_colored = colorize_filename(entry, &filename, config);
+        filename_colored.push_str(&tree_entry_suffix(tree_entry));
+        let marker = git_marker(entry, config);
 
         if icon.is_empty() {
-            println!("{} {}", tree_entry.branch, filename_colored);
+            writeln!(out, "{} {marker}{filename_colored}", tree_entry.branch)?;
         } else {
-            println!(
-                "{} {} {}",
+            writeln!(
+                out,
+                "{} {marker}{} {}",
                 tree_entry.branch,
-                icon.color(entry.get_icon_color(&config.icons.colors)),
+                entry.get_icon_color(&config.icons.colors).paint(&icon),
                 filename_colored
-            );
+            )?;
         }
     }
+
+    Ok(())
+}
+
+/// Follows a directory cut off by the depth limit, e.g. ` … 42 entries`, or
+/// one that could not be read, e.g. ` [Permission denied]`.
+fn tree_entry_suffix(tree_entry: &TreeEntry) -> String {
+    match (&tree_entry.error, tree_entry.truncated) {
+        (Some(error), _) => format!(" [{error}]"),
+        (None, Some(1)) => " … 1 entry".to_string(),
+        (None, Some(count)) => format!(" … {count} entries"),
+        (None, None) => String::new(),
+    }
 }
 
 fn display_entry(entry: &FileEntry) -> FileEntry {
@@ -0,0 +1,212 @@
+use crate::config::Config;
+use crate::file_entry::FileEntry;
+use crate::formatter::recursive::{write_branch_tree, write_long_tree};
+use crate::formatter::tree::TreeNode;
+use crate::formatter::{write_long, write_one_per_line, write_short, write_short_compact};
+use crate::sort::{DefaultSort, SortOptions};
+use std::io::{self, Write};
+
+/// A text layout for listings, selected by name with `--format` or
+/// `display.format`.
+pub trait Formatter: Send + Sync {
+    /// The name that selects this formatter.
+    fn name(&self) -> &str;
+
+    /// Whether entries need owners, groups and the other fields that are
+    /// slower to read.
+    fn needs_full_metadata(&self) -> bool {
+        false
+    }
+
+    /// Whether entries are written with the columns in `long_format_fields`,
+    /// so that a `git` column there needs git status read.
+    fn uses_long_format_fields(&self) -> bool {
+        false
+    }
+
+    /// Whether selecting this formatter lists directories recursively, as `-r` does.
+    fn is_recursive(&self) -> bool {
+        false
+    }
+
+    /// How a tree is ordered when no sort is requested.
+    fn tree_sort(&self) -> DefaultSort {
+        DefaultSort::Name
+    }
+
+    /// Writes the entries of a directory, or the files named on the command line.
+    fn write_entries(
+        &self,
+        out: &mut dyn Write,
+        entries: Vec<FileEntry>,
+        config: &Config,
+        sort: SortOptions,
+    ) -> io::Result<()>;
+
+    /// Writes a recursive listing: the root entry with its filtered, sorted
+    /// contents as children. Draws branches like the `tree` formatter by default.
+    fn write_tree(&self, out: &mut dyn Write, tree: TreeNode, config: &Config) -> io::Result<()> {
+        write_branch_tree(out, tree, config)
+    }
+}
+
+/// Columns grouped by file type, or a grid with `layout = "grid"`.
+pub struct ShortFormatter;
+
+impl Formatter for ShortFormatter {
+    fn name(&self) -> &str {
+        "short"
+    }
+
+    fn write_entries(
+        &self,
+        out: &mut dyn Write,
+        entries: Vec<FileEntry>,
+        config: &Config,
+        sort: SortOptions,
+    ) -> io::Result<()> {
+        write_short(out, entries, config, sort)
+    }
+}
+
+/// Columns of at most `compact_max_rows` rows.
+pub struct CompactFormatter;
+
+impl Formatter for CompactFormatter {
+    fn name(&self) -> &str {
+        "compact"
+    }
+
+    fn write_entries(
+        &self,
+        out: &mut dyn Write,
+        entries: Vec<FileEntry>,
+        config: &Config,
+        sort: SortOptions,
+    ) -> io::Result<()> {
+        write_short_compact(out, entries, config, sort)
+    }
+}
+
+/// One entry per line, like `ls -1`.
+pub struct OnePerLineFormatter;
+
+impl Formatter for OnePerLineFormatter {
+    fn name(&self) -> &str {
+        "one-per-line"
+    }
+
+    fn write_entries(
+        &self,
+        out: &mut dyn Write,
+        entries: Vec<FileEntry>,
+        config: &Config,
+        sort: SortOptions,
+    ) -> io::Result<()> {
+        write_one_per_line(out, entries, config, sort)
+    }
+}
+
+/// The columns in `long_format_fields`, like `ls -l`, with tree branches
+/// before the names of a recursive listing.
+pub struct LongFormatter;
+
+impl Formatter for LongFormatter {
+    fn name(&self) -> &str {
+        "long"
+    }
+
+    fn needs_full_metadata(&self) -> bool {
+        true
+    }
+
+    fn uses_long_format_fields(&self) -> bool {
+        true
+    }
+
+    fn tree_sort(&self) -> DefaultSort {
+        DefaultSort::TypeThenName
+    }
+
+    fn write_entries(
+        &self,
+        out: &mut dyn Write,
+        entries: Vec<FileEntry>,
+        config: &Config,
+        sort: SortOptions,
+    ) -> io::Result<()> {
+        write_long(out, entries, config, sort)
+    }
+
+    fn write_tree(&self, out: &mut dyn Write, tree: TreeNode, config: &Config) -> io::Result<()> {
+        write_long_tree(out, tree, config)
+    }
+}
+
+/// A directory tree with branches, as `-r` shows. Files named on the command
+/// line are listed one per line.
+pub struct TreeFormatter;
+
+impl Formatter for TreeFormatter {
+    fn name(&self) -> &str {
+        "tree"
+    }
+
+    fn is_recursive(&self) -> bool {
+        true
+    }
+
+    fn write_entries(
+        &self,
+        out: &mut dyn Write,
+        entries: Vec<FileEntry>,
+        config: &Config,
+        sort: SortOptions,
+    ) -> io::Result<()> {
+        write_one_per_line(out, entries, config, sort)
+    }
+}
+
+/// The formatters `--format` can select, by name. Starts with the built-in
+/// ones; downstream crates can register their own.
+pub struct FormatterRegistry {
+    formatters: Vec<Box<dyn Formatter>>,
+}
+
+impl Default for FormatterRegistry {
+    fn default() -> Self {
+        Self {
+            formatters: vec![
+                Box::new(ShortFormatter),
+                Box::new(CompactFormatter),
+                Box::new(OnePerLineFormatter),
+                Box::new(LongFormatter),
+                Box::new(TreeFormatter),
+            ],
+        }
+    }
+}
+
+impl FormatterRegistry {
+    /// Adds `formatter`, replacing any registered under the same name.
+    pub fn register(&mut self, formatter: impl Formatter + 'static) {
+        self.formatters
+            .retain(|registered| registered.name() != formatter.name());
+        self.formatters.push(Box::new(formatter));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Formatter> {
+        self.formatters
+            .iter()
+            .find(|formatter| formatter.name() == name)
+            .map(Box::as_ref)
+    }
+
+    /// The registered names, in registration order.
+    pub fn names(&self) -> Vec<&str> {
+        self.formatters
+            .iter()
+            .map(|formatter| formatter.name())
+            .collect()
+    }
+}
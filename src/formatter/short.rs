@@ -1,23 +1,90 @@
-use crate::config::Config;
-use crate::file_entry::{FileEntry, FileType};
+use crate::config::{Config, ShortLayout};
+use crate::file_entry::FileEntry;
+use crate::formatter::{colorize_filename, git_marker, git_marker_width, group_by_file_type};
 use crate::sort::{SortOptions, sort_entries};
-use colored::{ColoredString, Colorize};
+use crate::terminal::terminal_width;
+use std::io::{self, Write};
+use std::slice;
 use unicode_width::UnicodeWidthStr;
 
-pub fn format_short(entries: Vec<FileEntry>, config: &Config, sort: SortOptions) {
-    format_short_with_max_rows(entries, config, config.display.max_rows, sort);
+/// Like [`write_short`], but prints to stdout.
+pub fn format_short(entries: Vec<FileEntry>, config: &Config, sort: SortOptions) -> io::Result<()> {
+    write_short(&mut io::stdout().lock(), entries, config, sort)
 }
 
-pub fn format_short_compact(entries: Vec<FileEntry>, config: &Config, sort: SortOptions) {
-    format_short_with_max_rows(entries, config, config.display.compact_max_rows, sort);
+/// Writes entries in the configured short layout: columns of at most
+/// `max_rows` rows, or a grid fitted to the terminal width.
+pub fn write_short(
+    out: &mut dyn Write,
+    entries: Vec<FileEntry>,
+    config: &Config,
+    sort: SortOptions,
+) -> io::Result<()> {
+    match config.display.layout {
+        ShortLayout::Columns => {
+            write_short_with_max_rows(out, entries, config, config.display.max_rows, sort)
+        }
+        ShortLayout::Grid => write_short_grid(out, entries, config, sort),
+    }
+}
+
+/// Like [`write_short_grid`], but prints to stdout.
+pub fn format_short_grid(
+    entries: Vec<FileEntry>,
+    config: &Config,
+    sort: SortOptions,
+) -> io::Result<()> {
+    write_short_grid(&mut io::stdout().lock(), entries, config, sort)
+}
+
+/// Packs entries into as many columns as fit the terminal width, like `ls -C`,
+/// while keeping the per-type grouping of the default sort.
+pub fn write_short_grid(
+    out: &mut dyn Write,
+    mut entries: Vec<FileEntry>,
+    config: &Config,
+    sort: SortOptions,
+) -> io::Result<()> {
+    sort_entries(&mut entries, sort);
+    let column_spacing = config.display.column_spacing;
+    let width = terminal_width();
+
+    if sort.is_custom() {
+        let rows = fit_rows_to_width(slice::from_ref(&entries), column_spacing, width, config);
+        return write_sorted_entries_with_max_rows(out, entries, rows, column_spacing, config);
+    }
+
+    let sections = group_by_file_type(entries);
+    let rows = fit_rows_to_width(&sections, column_spacing, width, config);
+    write_with_max_rows(out, &sections, rows, column_spacing, config)
+}
+
+/// Like [`write_short_compact`], but prints to stdout.
+pub fn format_short_compact(
+    entries: Vec<FileEntry>,
+    config: &Config,
+    sort: SortOptions,
+) -> io::Result<()> {
+    write_short_compact(&mut io::stdout().lock(), entries, config, sort)
+}
+
+/// Writes entries in columns of at most `compact_max_rows` rows.
+pub fn write_short_compact(
+    out: &mut dyn Write,
+    entries: Vec<FileEntry>,
+    config: &Config,
+    sort: SortOptions,
+) -> io::Result<()> {
+    write_short_with_max_rows(out, entries, config, config.display.compact_max_rows, sort)
 }
 
-fn format_short_with_max_rows(
+fn write_short_with_max_rows(
+    out: &mut dyn Write,
     mut entries: Vec<FileEntry>,
     config: &Config,
     max_rows: usize,
     sort: SortOptions,
-) {
+) -> io::Result<()> {
     sort_entries(&mut entries, sort);
 
     if sort.is_custom() {
@@ -26,51 +93,77 @@ fn format_short_with_max_rows(
         } else {
             max_rows
         };
-        format_sorted_entries_with_max_rows(entries, rows, config.display.column_spacing, config);
-        return;
-    }
-
-    let mut directories: Vec<FileEntry> = Vec::new();
-    let mut executables: Vec<FileEntry> = Vec::new();
-    let mut regular_files: Vec<FileEntry> = Vec::new();
-
-    for entry in entries {
-        match entry.get_file_type() {
-            FileType::Directory => directories.push(entry),
-            FileType::Executable => executables.push(entry),
-            FileType::RegularFile => regular_files.push(entry),
-        }
+        let column_spacing = config.display.column_spacing;
+        return write_sorted_entries_with_max_rows(out, entries, rows, column_spacing, config);
     }
 
-    let column_spacing = config.display.column_spacing;
+    let sections = group_by_file_type(entries);
     // If max_rows is set (not 0), format each file type with wrapping
-    // Otherwise, use the original single-column-per-type format
-    if max_rows > 0 {
-        format_with_max_rows(
-            directories,
-            executables,
-            regular_files,
-            max_rows,
-            column_spacing,
-            config,
-        );
+    // Otherwise, give each file type a single column
+    let rows = if max_rows > 0 {
+        max_rows
     } else {
-        format_single_column_per_type(
-            directories,
-            executables,
-            regular_files,
-            column_spacing,
-            config,
-        );
-    }
+        sections.iter().map(Vec::len).max().unwrap_or(0)
+    };
+
+    write_with_max_rows(out, &sections, rows, config.display.column_spacing, config)
+}
+
+/// Finds the fewest rows for which the side-by-side sections fit in `width`,
+/// falling back to a single column per section when nothing narrower fits.
+/// Always at least 1, even with no entries to lay out.
+fn fit_rows_to_width(
+    sections: &[Vec<FileEntry>],
+    column_spacing: usize,
+    width: usize,
+    config: &Config,
+) -> usize {
+    let entry_widths: Vec<Vec<usize>> = sections
+        .iter()
+        .filter(|entries| !entries.is_empty())
+        .map(|entries| {
+            entries
+                .iter()
+                .map(|entry| entry_width(entry, config))
+                .collect()
+        })
+        .collect();
+    let max_rows = entry_widths.iter().map(Vec::len).max().unwrap_or(1);
+
+    (1..max_rows)
+        .find(|&rows| {
+            // Every column needs at least one character plus spacing, so skip
+            // row counts that cannot fit before measuring them
+            let columns: usize = entry_widths
+                .iter()
+                .map(|widths| widths.len().div_ceil(rows))
+                .sum();
+            if columns * (column_spacing + 1) > width + column_spacing {
+                return false;
+            }
+
+            let line_width: usize = entry_widths
+                .iter()
+                .map(|widths| {
+                    widths
+                        .chunks(rows)
+                        .map(|column| column.iter().copied().max().unwrap_or(0))
+                        .sum::<usize>()
+                })
+                .sum::<usize>()
+                + columns.saturating_sub(1) * column_spacing;
+            line_width <= width
+        })
+        .unwrap_or(max_rows)
 }
 
-fn format_sorted_entries_with_max_rows(
+fn write_sorted_entries_with_max_rows(
+    out: &mut dyn Write,
     entries: Vec<FileEntry>,
     max_rows: usize,
     column_spacing: usize,
     config: &Config,
-) {
+) -> io::Result<()> {
     let widths = compact_column_widths(&entries, max_rows, config);
 
     for row in 0..max_rows {
@@ -93,18 +186,16 @@ fn format_sorted_entries_with_max_rows(
         }
 
         if has_content {
-            println!("{}", line.trim_end());
+            writeln!(out, "{}", line.trim_end())?;
         }
     }
+
+    Ok(())
 }
 
-fn format_entry(
-    icon: &str,
-    icon_colored: ColoredString,
-    filename_colored: ColoredString,
-) -> String {
+fn format_entry(icon: &str, icon_colored: String, filename_colored: String) -> String {
     if icon.is_empty() {
-        format!("{}", filename_colored)
+        filename_colored
     } else {
         format!("{} {}", icon_colored, filename_colored)
     }
@@ -116,22 +207,17 @@ fn display_width(icon: &str, filename: &str) -> usize {
         + UnicodeWidthStr::width(filename)
 }
 
-fn format_with_max_rows(
-    directories: Vec<FileEntry>,
-    executables: Vec<FileEntry>,
-    regular_files: Vec<FileEntry>,
+fn write_with_max_rows(
+    out: &mut dyn Write,
+    sections: &[Vec<FileEntry>],
     max_rows: usize,
     column_spacing: usize,
     config: &Config,
-) {
-    let directory_widths = compact_column_widths(&directories, max_rows, config);
-    let executable_widths = compact_column_widths(&executables, max_rows, config);
-    let file_widths = compact_column_widths(&regular_files, max_rows, config);
-    let sections = [
-        (&directories, directory_widths),
-        (&executables, executable_widths),
-        (&regular_files, file_widths),
-    ];
+) -> io::Result<()> {
+    let sections: Vec<(&Vec<FileEntry>, Vec<usize>)> = sections
+        .iter()
+        .map(|entries| (entries, compact_column_widths(entries, max_rows, config)))
+        .collect();
 
     // Print rows, with all file types side-by-side
     for row in 0..max_rows {
@@ -160,9 +246,11 @@ fn format_with_max_rows(
         }
 
         if has_any_content {
-            println!("{}", line.trim_end());
+            writeln!(out, "{}", line.trim_end())?;
         }
     }
+
+    Ok(())
 }
 
 fn compact_column_widths(entries: &[FileEntry], max_rows: usize, config: &Config) -> Vec<usize> {
@@ -212,148 +300,18 @@ fn append_compact_section(
 fn entry_width(entry: &FileEntry, config: &Config) -> usize {
     let filename = entry.path.to_string_lossy();
     let icon = entry.get_icon_custom(&config.icons);
-    display_width(icon.as_str(), filename.as_ref())
+    git_marker_width(config) + display_width(icon.as_str(), filename.as_ref())
 }
 
 fn format_short_entry(entry: &FileEntry, config: &Config) -> String {
     let filename = entry.path.to_string_lossy();
     let icon = entry.get_icon_custom(&config.icons);
-    let filename_colored = match entry.get_file_type() {
-        FileType::Directory | FileType::Executable => {
-            filename.color(entry.get_color(&config.colors)).bold()
-        }
-        FileType::RegularFile => filename.color(entry.get_color(&config.colors)),
-    };
+    let filename_colored = colorize_filename(entry, &filename, config);
 
-    format_entry(
+    let formatted = format_entry(
         icon.as_str(),
-        icon.color(entry.get_icon_color(&config.icons.colors)),
+        entry.get_icon_color(&config.icons.colors).paint(&icon),
         filename_colored,
-    )
-}
-
-fn format_single_column_per_type(
-    directories: Vec<FileEntry>,
-    executables: Vec<FileEntry>,
-    regular_files: Vec<FileEntry>,
-    column_spacing: usize,
-    config: &Config,
-) {
-    // Calculate column widths (icon + space + filename)
-    let dir_width = directories
-        .iter()
-        .map(|e| {
-            let filename = e.path.to_string_lossy();
-            let icon = e.get_icon_custom(&config.icons);
-            display_width(icon.as_str(), filename.as_ref())
-        })
-        .max()
-        .unwrap_or(0);
-
-    let exec_width = executables
-        .iter()
-        .map(|e| {
-            let filename = e.path.to_string_lossy();
-            let icon = e.get_icon_custom(&config.icons);
-            display_width(icon.as_str(), filename.as_ref())
-        })
-        .max()
-        .unwrap_or(0);
-
-    let file_width = regular_files
-        .iter()
-        .map(|e| {
-            let filename = e.path.to_string_lossy();
-            let icon = e.get_icon_custom(&config.icons);
-            display_width(icon.as_str(), filename.as_ref())
-        })
-        .max()
-        .unwrap_or(0);
-
-    // Determine how many rows we need
-    let max_rows = *[directories.len(), executables.len(), regular_files.len()]
-        .iter()
-        .max()
-        .unwrap_or(&0);
-
-    // Print side-by-side columns
-    for i in 0..max_rows {
-        let mut line = String::new();
-
-        // Directory column
-        if dir_width > 0 {
-            if i < directories.len() {
-                let entry = &directories[i];
-                let filename = entry.path.to_string_lossy();
-                let icon = entry.get_icon_custom(&config.icons);
-                let actual_width = display_width(icon.as_str(), filename.as_ref());
-
-                line.push_str(&format_entry(
-                    icon.as_str(),
-                    icon.color(entry.get_icon_color(&config.icons.colors)),
-                    filename.color(entry.get_color(&config.colors)).bold(),
-                ));
-                // Add padding after the colored text
-                if actual_width < dir_width {
-                    line.push_str(&" ".repeat(dir_width - actual_width));
-                }
-            } else {
-                // Empty space for this row in directory column
-                line.push_str(&" ".repeat(dir_width));
-            }
-
-            // Add spacing after directory column if there are more columns
-            if exec_width > 0 || file_width > 0 {
-                line.push_str(&" ".repeat(column_spacing));
-            }
-        }
-
-        // Executable column
-        if exec_width > 0 {
-            if i < executables.len() {
-                let entry = &executables[i];
-                let filename = entry.path.to_string_lossy();
-                let icon = entry.get_icon_custom(&config.icons);
-                let actual_width = display_width(icon.as_str(), filename.as_ref());
-
-                line.push_str(&format_entry(
-                    icon.as_str(),
-                    icon.color(entry.get_icon_color(&config.icons.colors)),
-                    filename.color(entry.get_color(&config.colors)).bold(),
-                ));
-                // Add padding after the colored text
-                if actual_width < exec_width {
-                    line.push_str(&" ".repeat(exec_width - actual_width));
-                }
-            } else {
-                // Empty space for this row in executable column
-                line.push_str(&" ".repeat(exec_width));
-            }
-
-            // Add spacing after executable column if there are regular files
-            if file_width > 0 {
-                line.push_str(&" ".repeat(column_spacing));
-            }
-        }
-
-        // Regular files column
-        if i < regular_files.len() {
-            let entry = &regular_files[i];
-            let filename = entry.path.to_string_lossy();
-            let icon = entry.get_icon_custom(&config.icons);
-            let actual_width = display_width(icon.as_str(), filename.as_ref());
-
-            line.push_str(&format_entry(
-                icon.as_str(),
-                icon.color(entry.get_icon_color(&config.icons.colors)),
-                filename.color(entry.get_color(&config.colors)),
-            ));
-            // Add padding after the colored text
-            if actual_width < file_width {
-                line.push_str(&" ".repeat(file_width - actual_width));
-            }
-        }
-
-        println!("{}", line.trim_end());
-    }
+    );
+    format!("{}{formatted}", git_marker(entry, config))
 }
@@ -1,97 +1,496 @@
+use crate::error::{LxError, Operation};
 use crate::file_entry::FileEntry;
 use crate::filter::EntryFilter;
-use crate::reader::{MetadataMode, read_directory_entries};
+use crate::git::GitStatuses;
+use crate::ignore::IgnoreRules;
+use crate::reader::{DiscoveredEntry, MetadataMode, Partial, read_directory_entries, read_entry};
+use crate::report::error_message;
 use crate::sort::{DefaultSort, SortOptions, sort_discovered_entries};
+use serde::Serialize;
 use std::io;
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
 
+/// A tree entry flattened into a line, with the branch drawn before it.
 pub struct TreeEntry {
     pub entry: FileEntry,
     pub branch: String,
+    /// How many entries a directory cut off by the depth limit holds
+    pub truncated: Option<usize>,
+    /// Why a directory's contents could not be read
+    pub error: Option<String>,
 }
 
+/// A filtered, sorted entry together with the contents of its directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNode {
+    #[serde(flatten)]
+    pub entry: FileEntry,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<TreeNode>>,
+    /// How many entries a directory cut off by the depth limit holds
+    #[serde(rename = "truncated_entries", skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<usize>,
+    /// Why a directory's contents could not be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// How targets are read: which entries are kept and in what order, whether
+/// they carry git status, and how deep trees go. The command line and the
+/// library entry points such as [`write_recursive`](crate::write_recursive)
+/// read through the same options.
+#[derive(Clone, Copy)]
+pub struct TreeOptions<'a> {
+    pub show_hidden: bool,
+    pub filter: &'a EntryFilter,
+    pub sort: SortOptions,
+    pub git_statuses: Option<&'a GitStatuses>,
+    pub max_depth: Option<usize>,
+}
+
+impl<'a> TreeOptions<'a> {
+    /// Options keeping what `filter` lets through, sorted by `sort`, without
+    /// hidden entries, git status or a depth limit.
+    pub fn new(filter: &'a EntryFilter, sort: SortOptions) -> Self {
+        Self {
+            show_hidden: false,
+            filter,
+            sort,
+            git_statuses: None,
+            max_depth: None,
+        }
+    }
+
+    /// A renderer collecting trees with these options, ordering entries by
+    /// `default_sort` unless `sort` is custom.
+    pub fn renderer(&self, default_sort: DefaultSort) -> TreeRenderer<'a> {
+        TreeRenderer::new(self.filter, self.sort, default_sort)
+            .with_git_statuses(self.git_statuses)
+            .with_max_depth(self.max_depth)
+    }
+}
+
+/// Collects the filtered, sorted contents of a directory tree.
 pub struct TreeRenderer<'a> {
-    style: &'a str,
-    indents_for_icons: bool,
     filter: &'a EntryFilter,
     sort: SortOptions,
     default_sort: DefaultSort,
+    git_statuses: Option<&'a GitStatuses>,
+    max_depth: Option<usize>,
+    threads: usize,
 }
 
 impl<'a> TreeRenderer<'a> {
-    pub fn new(
-        style: &'a str,
-        indents_for_icons: bool,
-        filter: &'a EntryFilter,
-        sort: SortOptions,
-        default_sort: DefaultSort,
-    ) -> Self {
+    pub fn new(filter: &'a EntryFilter, sort: SortOptions, default_sort: DefaultSort) -> Self {
+        // Reading directories mostly waits on the file system, so a few
+        // threads help even on a single core
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
         Self {
-            style,
-            indents_for_icons,
             filter,
             sort,
             default_sort,
+            git_statuses: None,
+            max_depth: None,
+            threads: threads.max(MIN_DEFAULT_THREADS),
         }
     }
 
-    pub fn collect(
+    /// Reads up to `threads` directories at once, counting the calling
+    /// thread, which starts `threads - 1` workers per tree; 1 walks the tree
+    /// on the calling thread alone. Defaults to the available parallelism,
+    /// but at least 4.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Stops descending below `max_depth` levels, counting the entries of
+    /// directories at the limit instead.
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets each collected entry's git status from `git_statuses`.
+    pub fn with_git_statuses(mut self, git_statuses: Option<&'a GitStatuses>) -> Self {
+        self.git_statuses = git_statuses;
+        self
+    }
+
+    /// Reads `path` and everything below it as a tree, or `None` when the
+    /// filter hides `path` itself. Entries that cannot be read are left out
+    /// and unreadable subdirectories marked with their `error`; the problems
+    /// are returned in tree order for the caller to report.
+    pub fn collect_tree(
         &self,
         path: &Path,
         show_hidden: bool,
         metadata_mode: MetadataMode,
-    ) -> io::Result<Vec<TreeEntry>> {
-        let mut tree_entries = Vec::new();
-        self.collect_directory(path, show_hidden, metadata_mode, "", &mut tree_entries)?;
-        Ok(tree_entries)
+    ) -> io::Result<Partial<Option<TreeNode>>> {
+        let root = read_entry(path, metadata_mode)?;
+        if !self.filter.includes(&root) {
+            return Ok(Partial::new(None));
+        }
+
+        let children = self.collect_nodes(path, show_hidden, metadata_mode)?;
+        Ok(children.map(|children| {
+            Some(TreeNode {
+                entry: root,
+                children: Some(children),
+                truncated: None,
+                error: None,
+            })
+        }))
     }
 
-    fn collect_directory(
+    pub fn collect_nodes(
         &self,
         path: &Path,
         show_hidden: bool,
         metadata_mode: MetadataMode,
-        prefix: &str,
-        tree_entries: &mut Vec<TreeEntry>,
-    ) -> io::Result<()> {
-        let mut entries = read_directory_entries(path, show_hidden, metadata_mode)?;
-        entries.retain(|entry| self.filter.includes(&entry.entry));
+    ) -> io::Result<Partial<Vec<TreeNode>>> {
+        let ignore_rules = self.filter.ignore_rules(path);
+        let pool = Pool::new(self.threads);
+        thread::scope(|scope| {
+            for _ in 0..pool.workers {
+                scope.spawn(|| pool.work());
+            }
+            // Stops the workers however collecting ends, so the scope can join them
+            let _close = ClosePool(&pool);
+            self.collect_directory(
+                path,
+                show_hidden,
+                metadata_mode,
+                ignore_rules.as_ref(),
+                1,
+                &pool,
+            )
+        })
+    }
+
+    /// Reads, filters and sorts one directory, then recurses into its
+    /// subdirectories with their own ignore files added to `ignore_rules`.
+    /// Entries of `path` are at `depth`, counting the root's entries as 1.
+    fn collect_directory<'s>(
+        &'s self,
+        path: &Path,
+        show_hidden: bool,
+        metadata_mode: MetadataMode,
+        ignore_rules: Option<&IgnoreRules>,
+        depth: usize,
+        pool: &Pool<'s>,
+    ) -> io::Result<Partial<Vec<TreeNode>>> {
+        let Partial {
+            value: mut entries,
+            mut problems,
+        } = self.read_directory(path, show_hidden, metadata_mode, ignore_rules)?;
         sort_discovered_entries(&mut entries, self.sort, self.default_sort);
+        if let Some(git_statuses) = self.git_statuses {
+            git_statuses.annotate(
+                path,
+                entries.iter_mut().map(|discovered| &mut discovered.entry),
+            );
+        }
 
-        for (index, discovered_entry) in entries.iter().enumerate() {
-            let is_last = index == entries.len() - 1;
-            let (connector, child_prefix) = self.branch(prefix, is_last);
+        let descends = self.max_depth.is_none_or(|max_depth| depth < max_depth);
+        // Subdirectories go to an idle worker while there is one and are
+        // collected on this thread otherwise; either way every node keeps its
+        // place in the sorted order
+        let pending: Vec<PendingNode> = entries
+            .into_iter()
+            .map(|discovered_entry| {
+                if !(descends && discovered_entry.entry.is_dir) {
+                    return PendingNode::Collected(Box::new(self.collect_node(
+                        discovered_entry,
+                        show_hidden,
+                        metadata_mode,
+                        ignore_rules,
+                        depth,
+                        pool,
+                    )));
+                }
 
-            tree_entries.push(TreeEntry {
-                entry: discovered_entry.entry.clone(),
-                branch: format!("{prefix}{connector}"),
+                let ignore_rules = ignore_rules.cloned();
+                let collect = move |pool: &Pool<'s>| {
+                    self.collect_node(
+                        discovered_entry,
+                        show_hidden,
+                        metadata_mode,
+                        ignore_rules.as_ref(),
+                        depth,
+                        pool,
+                    )
+                };
+                match pool.try_spawn(collect) {
+                    Ok(result) => PendingNode::Spawned(result),
+                    Err(collect) => PendingNode::Collected(Box::new(collect(pool))),
+                }
+            })
+            .collect();
+
+        let nodes = pending
+            .into_iter()
+            .map(|node| {
+                let node = match node {
+                    PendingNode::Collected(node) => *node,
+                    PendingNode::Spawned(result) => match result.recv() {
+                        Ok(Ok(node)) => node,
+                        Ok(Err(panic)) => panic::resume_unwind(panic),
+                        Err(_) => unreachable!("workers run every job before the pool closes"),
+                    },
+                };
+                problems.extend(node.problems);
+                node.value
+            })
+            .collect();
+        Ok(Partial {
+            value: nodes,
+            problems,
+        })
+    }
+
+    /// Turns one entry of a directory at `depth` into a node, collecting the
+    /// contents of a subdirectory unless it is at the depth limit.
+    fn collect_node<'s>(
+        &'s self,
+        discovered_entry: DiscoveredEntry,
+        show_hidden: bool,
+        metadata_mode: MetadataMode,
+        ignore_rules: Option<&IgnoreRules>,
+        depth: usize,
+        pool: &Pool<'s>,
+    ) -> Partial<TreeNode> {
+        if !discovered_entry.entry.is_dir {
+            return Partial::new(TreeNode {
+                entry: discovered_entry.entry,
+                children: None,
+                truncated: None,
+                error: None,
             });
+        }
 
-            if discovered_entry.entry.is_dir {
-                self.collect_directory(
-                    &discovered_entry.full_path,
+        let child_rules =
+            ignore_rules.map(|rules| rules.child(&discovered_entry.entry.path.to_string_lossy()));
+        let full_path = &discovered_entry.full_path;
+        if self.max_depth.is_some_and(|max_depth| depth >= max_depth) {
+            // Unreadable directories past the limit are simply not counted
+            let truncated = self
+                .read_directory(
+                    full_path,
                     show_hidden,
-                    metadata_mode,
-                    &child_prefix,
-                    tree_entries,
-                )?;
+                    MetadataMode::Basic,
+                    child_rules.as_ref(),
+                )
+                .ok()
+                .map(|entries| entries.value.len())
+                .filter(|&count| count > 0);
+            return Partial::new(TreeNode {
+                entry: discovered_entry.entry,
+                children: None,
+                truncated,
+                error: None,
+            });
+        }
+
+        // An unreadable subdirectory is marked, and the rest of the tree still listed
+        let (children, error, problems) = match self.collect_directory(
+            full_path,
+            show_hidden,
+            metadata_mode,
+            child_rules.as_ref(),
+            depth + 1,
+            pool,
+        ) {
+            Ok(children) => (Some(children.value), None, children.problems),
+            Err(error) => {
+                let message = error_message(&error);
+                let problem = LxError::io(full_path, Operation::OpenDirectory, error);
+                (None, Some(message), vec![problem])
             }
+        };
+        Partial {
+            value: TreeNode {
+                entry: discovered_entry.entry,
+                children,
+                truncated: None,
+                error,
+            },
+            problems,
+        }
+    }
+
+    /// The entries of `path` that pass the filter and ignore rules, unsorted.
+    fn read_directory(
+        &self,
+        path: &Path,
+        show_hidden: bool,
+        metadata_mode: MetadataMode,
+        ignore_rules: Option<&IgnoreRules>,
+    ) -> io::Result<Partial<Vec<DiscoveredEntry>>> {
+        let mut entries = read_directory_entries(path, show_hidden, metadata_mode)?;
+        entries.value.retain(|discovered_entry| {
+            let entry = &discovered_entry.entry;
+            self.filter.includes(entry)
+                && !ignore_rules.is_some_and(|rules| {
+                    rules.is_ignored(&entry.path.to_string_lossy(), entry.is_dir)
+                })
+        });
+        Ok(entries)
+    }
+}
+
+/// The fewest threads a tree is read with unless `with_threads` says otherwise.
+const MIN_DEFAULT_THREADS: usize = 4;
+
+/// A node collected on this thread, or still being collected on a worker.
+enum PendingNode {
+    Collected(Box<Partial<TreeNode>>),
+    Spawned(Receiver<thread::Result<Partial<TreeNode>>>),
+}
+
+/// A fixed set of worker threads for one tree, handed subdirectories to
+/// collect while one of them is idle. A job runs on a worker of its own, so
+/// one waiting for the subdirectories it handed out never holds up their
+/// collection.
+struct Pool<'s> {
+    workers: usize,
+    idle: AtomicUsize,
+    sender: Sender<Option<Job<'s>>>,
+    receiver: Mutex<Receiver<Option<Job<'s>>>>,
+}
+
+/// Collects a subdirectory on a worker, which passes the job its pool.
+struct Job<'s>(Box<dyn FnOnce(&Pool<'s>) + Send + 's>);
+
+impl<'s> Pool<'s> {
+    /// A pool for `threads` threads in all, one of them the calling thread.
+    fn new(threads: usize) -> Self {
+        let workers = threads.saturating_sub(1);
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            workers,
+            idle: AtomicUsize::new(workers),
+            sender,
+            receiver: Mutex::new(receiver),
         }
+    }
 
-        Ok(())
+    /// Hands `collect` to an idle worker, returning where its result will
+    /// arrive, or hands it back when every worker is busy.
+    fn try_spawn<T, F>(&self, collect: F) -> Result<Receiver<thread::Result<T>>, F>
+    where
+        T: Send + 's,
+        F: FnOnce(&Pool<'s>) -> T + Send + 's,
+    {
+        if self
+            .idle
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |idle| {
+                idle.checked_sub(1)
+            })
+            .is_err()
+        {
+            return Err(collect);
+        }
+
+        let (result_sender, result) = mpsc::channel();
+        let job = Job(Box::new(move |pool: &Pool<'s>| {
+            // Nobody waits for the result once the caller has panicked
+            let _ = result_sender.send(panic::catch_unwind(AssertUnwindSafe(|| collect(pool))));
+        }));
+        // The receiver lives as long as the pool, so sending cannot fail
+        let _ = self.sender.send(Some(job));
+        Ok(result)
     }
 
-    fn branch(&self, prefix: &str, is_last: bool) -> (&str, String) {
-        let content_indent = if self.indents_for_icons { "  " } else { " " };
+    /// Runs jobs until the pool is closed.
+    fn work(&self) {
+        loop {
+            let job = self
+                .receiver
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .recv();
+            let Ok(Some(Job(job))) = job else {
+                return;
+            };
+            job(self);
+            self.idle.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+}
 
-        if self.style == "ascii" {
-            if is_last {
-                ("╰─", format!("{prefix}  {content_indent}"))
-            } else {
-                ("├─", format!("{prefix}│ {content_indent}"))
-            }
+/// Stops every worker of the pool once dropped.
+struct ClosePool<'p, 's>(&'p Pool<'s>);
+
+impl Drop for ClosePool<'_, '_> {
+    fn drop(&mut self) {
+        for _ in 0..self.0.workers {
+            let _ = self.0.sender.send(None);
+        }
+    }
+}
+
+/// Flattens nodes into lines in `style` (`ascii` or `indent`), with branches
+/// indented further when the entries are drawn with icons.
+pub fn flatten_tree(nodes: Vec<TreeNode>, style: &str, indents_for_icons: bool) -> Vec<TreeEntry> {
+    let mut tree_entries = Vec::new();
+    flatten(nodes, style, indents_for_icons, "", &mut tree_entries);
+    tree_entries
+}
+
+fn flatten(
+    nodes: Vec<TreeNode>,
+    style: &str,
+    indents_for_icons: bool,
+    prefix: &str,
+    tree_entries: &mut Vec<TreeEntry>,
+) {
+    let node_count = nodes.len();
+
+    for (index, node) in nodes.into_iter().enumerate() {
+        let is_last = index == node_count - 1;
+        let (connector, child_prefix) = branch(style, indents_for_icons, prefix, is_last);
+
+        tree_entries.push(TreeEntry {
+            entry: node.entry,
+            branch: format!("{prefix}{connector}"),
+            truncated: node.truncated,
+            error: node.error,
+        });
+
+        if let Some(children) = node.children {
+            flatten(
+                children,
+                style,
+                indents_for_icons,
+                &child_prefix,
+                tree_entries,
+            );
+        }
+    }
+}
+
+fn branch(
+    style: &str,
+    indents_for_icons: bool,
+    prefix: &str,
+    is_last: bool,
+) -> (&'static str, String) {
+    let content_indent = if indents_for_icons { "  " } else { " " };
+
+    if style == "ascii" {
+        if is_last {
+            ("╰─", format!("{prefix}  {content_indent}"))
         } else {
-            ("", format!("{prefix}  {content_indent}"))
+            ("├─", format!("{prefix}│ {content_indent}"))
         }
+    } else {
+        ("", format!("{prefix}  {content_indent}"))
     }
 }
@@ -0,0 +1,295 @@
+/// Git working-tree and index status for entries inside local repositories,
+/// read with one `git status` run per repository.
+use crate::file_entry::FileEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// How an entry differs from the last commit, in increasing order of importance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GitChange {
+    Ignored,
+    New,
+    TypeChange,
+    Renamed,
+    Deleted,
+    Modified,
+    Conflicted,
+}
+
+impl GitChange {
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            b'A' | b'?' => Some(GitChange::New),
+            b'M' => Some(GitChange::Modified),
+            b'D' => Some(GitChange::Deleted),
+            b'R' | b'C' => Some(GitChange::Renamed),
+            b'T' => Some(GitChange::TypeChange),
+            b'!' => Some(GitChange::Ignored),
+            b'U' => Some(GitChange::Conflicted),
+            _ => None,
+        }
+    }
+
+    /// The one-letter marker shown for this change.
+    pub fn marker(self) -> char {
+        match self {
+            GitChange::Ignored => 'I',
+            GitChange::New => 'N',
+            GitChange::TypeChange => 'T',
+            GitChange::Renamed => 'R',
+            GitChange::Deleted => 'D',
+            GitChange::Modified => 'M',
+            GitChange::Conflicted => 'U',
+        }
+    }
+}
+
+/// An entry's change in the index (staged) and in the working tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct GitStatus {
+    pub staged: Option<GitChange>,
+    pub unstaged: Option<GitChange>,
+}
+
+impl GitStatus {
+    /// Parses the `XY` code of a `git status --porcelain` line.
+    fn from_codes(index: u8, worktree: u8) -> Self {
+        let conflicted = matches!(
+            (index, worktree),
+            (b'U', _) | (_, b'U') | (b'A', b'A') | (b'D', b'D')
+        );
+        if conflicted {
+            return GitStatus {
+                staged: Some(GitChange::Conflicted),
+                unstaged: Some(GitChange::Conflicted),
+            };
+        }
+
+        match (index, worktree) {
+            (b'?', b'?') | (b'!', b'!') => GitStatus {
+                staged: None,
+                unstaged: GitChange::from_code(worktree),
+            },
+            _ => GitStatus {
+                staged: GitChange::from_code(index),
+                unstaged: GitChange::from_code(worktree),
+            },
+        }
+    }
+
+    /// Combines two statuses, keeping the more important change on each side.
+    fn merge(self, other: Self) -> Self {
+        GitStatus {
+            staged: self.staged.max(other.staged),
+            unstaged: self.unstaged.max(other.unstaged),
+        }
+    }
+
+    /// The most important change, preferring unstaged changes over staged ones.
+    pub fn primary_change(&self) -> Option<GitChange> {
+        match (self.staged, self.unstaged) {
+            (Some(GitChange::Conflicted), _) => Some(GitChange::Conflicted),
+            (staged, None) => staged,
+            (_, unstaged) => unstaged,
+        }
+    }
+}
+
+/// The status of every changed path in one repository.
+#[derive(Debug)]
+struct Repository {
+    root: PathBuf,
+    /// Changed files, relative to the root
+    statuses: HashMap<PathBuf, GitStatus>,
+    /// Directories git reports as a whole, such as ignored ones, relative to the root
+    directories: HashMap<PathBuf, GitStatus>,
+    /// The merged status of everything below each directory holding a change
+    rollups: HashMap<PathBuf, GitStatus>,
+}
+
+impl Repository {
+    fn read(root: &Path) -> Option<Self> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args([
+                "status",
+                "--porcelain=v1",
+                "-z",
+                "--ignored",
+                "--untracked-files=normal",
+            ])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+
+        let mut repository = Repository {
+            root: root.to_path_buf(),
+            statuses: HashMap::new(),
+            directories: HashMap::new(),
+            rollups: HashMap::new(),
+        };
+        let mut records = output.stdout.split(|&byte| byte == 0);
+        while let Some(record) = records.next() {
+            let [index, worktree, b' ', path @ ..] = record else {
+                continue;
+            };
+            // Renames and copies are followed by their original path
+            if matches!(index, b'R' | b'C') {
+                records.next();
+            }
+
+            let status = GitStatus::from_codes(*index, *worktree);
+            let path = String::from_utf8_lossy(path);
+            let path = match path.strip_suffix('/') {
+                Some(directory) => {
+                    let directory = PathBuf::from(directory);
+                    merge_into(&mut repository.directories, &directory, status);
+                    directory
+                }
+                None => {
+                    let path = PathBuf::from(&*path);
+                    merge_into(&mut repository.statuses, &path, status);
+                    path
+                }
+            };
+
+            // Ignored children do not make their directory ignored
+            let not_ignored = |change: &GitChange| *change != GitChange::Ignored;
+            let rollup = GitStatus {
+                staged: status.staged.filter(not_ignored),
+                unstaged: status.unstaged.filter(not_ignored),
+            };
+            for ancestor in path.ancestors().skip(1) {
+                merge_into(&mut repository.rollups, ancestor, rollup);
+            }
+        }
+
+        Some(repository)
+    }
+
+    /// The status of a path, rolling up everything below it for directories.
+    fn status(&self, path: &Path, is_dir: bool) -> GitStatus {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return GitStatus::default();
+        };
+
+        // Everything inside a directory reported as a whole shares its status
+        let mut status = relative
+            .ancestors()
+            .filter_map(|ancestor| self.directories.get(ancestor))
+            .fold(GitStatus::default(), |status, directory_status| {
+                status.merge(*directory_status)
+            });
+        if let Some(own_status) = self.statuses.get(relative) {
+            status = status.merge(*own_status);
+        }
+        match self.rollups.get(relative) {
+            Some(rollup) if is_dir => status.merge(*rollup),
+            _ => status,
+        }
+    }
+}
+
+/// Merges `status` into the one recorded for `path`.
+fn merge_into(statuses: &mut HashMap<PathBuf, GitStatus>, path: &Path, status: GitStatus) {
+    let merged = statuses
+        .get(path)
+        .map_or(status, |known| known.merge(status));
+    statuses.insert(path.to_path_buf(), merged);
+}
+
+/// A canonical directory and the repository it belongs to.
+struct Located {
+    directory: PathBuf,
+    repository: Arc<Repository>,
+}
+
+/// Reads each repository's status the first time an entry inside it is
+/// looked up, and answers later lookups from memory.
+#[derive(Debug, Default)]
+pub struct GitStatuses {
+    repositories: Mutex<HashMap<PathBuf, Arc<RepositoryCell>>>,
+}
+
+/// A repository read at most once, or `None` when `git status` failed.
+type RepositoryCell = OnceLock<Option<Arc<Repository>>>;
+
+impl GitStatuses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the git status of entries read from `directory`, leaving it
+    /// unset for entries outside a repository.
+    pub fn annotate<'e>(
+        &self,
+        directory: &Path,
+        entries: impl IntoIterator<Item = &'e mut FileEntry>,
+    ) {
+        // Entries usually share a parent, so it is only resolved when it changes
+        let mut current: Option<(PathBuf, Option<Located>)> = None;
+
+        for entry in entries {
+            let path = directory.join(&entry.path);
+            let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+                continue;
+            };
+            if current.as_ref().is_none_or(|(known, _)| known != parent) {
+                current = Some((parent.to_path_buf(), self.locate(parent)));
+            }
+
+            entry.git_status = match current.as_ref().and_then(|(_, located)| located.as_ref()) {
+                // The repository root is never part of its own status
+                Some(located) if located.directory.join(name) != located.repository.root => {
+                    let path = located.directory.join(name);
+                    Some(located.repository.status(&path, entry.is_dir))
+                }
+                _ => None,
+            };
+        }
+    }
+
+    /// Resolves `directory` and the repository holding it, if there is one.
+    fn locate(&self, directory: &Path) -> Option<Located> {
+        let directory = if directory.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            directory
+        };
+        let directory = directory.canonicalize().ok()?;
+        let root = repository_root(&directory)?;
+        let repository = self.repository(&root)?;
+        Some(Located {
+            directory,
+            repository,
+        })
+    }
+
+    fn repository(&self, root: &Path) -> Option<Arc<Repository>> {
+        // `git status` runs outside the lock, so threads looking up other
+        // repositories do not wait for it
+        let cell = self
+            .repositories
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(root.to_path_buf())
+            .or_default()
+            .clone();
+        cell.get_or_init(|| Repository::read(root).map(Arc::new))
+            .clone()
+    }
+}
+
+/// The root of the git repository containing `directory`, found by looking
+/// for a `.git` directory or file in it and its ancestors.
+pub fn repository_root(directory: &Path) -> Option<PathBuf> {
+    directory
+        .ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+        .map(Path::to_path_buf)
+}
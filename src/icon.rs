@@ -1,12 +1,18 @@
 /// Handles file type icons and their associated colors.
 use crate::config::{ColorConfig, IconColorConfig, IconConfig};
-use colored::Color;
+use crate::style::Style;
 
 #[derive(Debug, Clone, Copy)]
 pub enum FileIcon {
     Directory,
     Executable,
     RegularFile,
+    Symlink,
+    BrokenSymlink,
+    BlockDevice,
+    CharDevice,
+    Fifo,
+    Socket,
 }
 
 impl FileIcon {
@@ -15,22 +21,132 @@ impl FileIcon {
             FileIcon::Directory => config.get_directory_icon(),
             FileIcon::Executable => config.get_executable_icon(),
             FileIcon::RegularFile => config.get_regular_icon(),
+            FileIcon::Symlink => config.get_symlink_icon(),
+            FileIcon::BrokenSymlink => config.get_broken_symlink_icon(),
+            FileIcon::BlockDevice => config.get_block_device_icon(),
+            FileIcon::CharDevice => config.get_char_device_icon(),
+            FileIcon::Fifo => config.get_fifo_icon(),
+            FileIcon::Socket => config.get_socket_icon(),
         }
     }
 
-    pub fn get_color(&self, config: &ColorConfig) -> Color {
+    pub fn get_color(&self, config: &ColorConfig) -> Style {
         match self {
             FileIcon::Directory => config.get_directory_color(),
             FileIcon::Executable => config.get_executable_color(),
             FileIcon::RegularFile => config.get_regular_color(),
+            FileIcon::Symlink => config.get_symlink_color(),
+            FileIcon::BrokenSymlink => config.get_broken_symlink_color(),
+            FileIcon::BlockDevice => config.get_block_device_color(),
+            FileIcon::CharDevice => config.get_char_device_color(),
+            FileIcon::Fifo => config.get_fifo_color(),
+            FileIcon::Socket => config.get_socket_color(),
         }
     }
 
-    pub fn get_icon_color(&self, config: &IconColorConfig) -> Color {
+    pub fn get_icon_color(&self, config: &IconColorConfig) -> Style {
         match self {
             FileIcon::Directory => config.get_directory_color(),
             FileIcon::Executable => config.get_executable_color(),
             FileIcon::RegularFile => config.get_regular_color(),
+            FileIcon::Symlink => config.get_symlink_color(),
+            FileIcon::BrokenSymlink => config.get_broken_symlink_color(),
+            FileIcon::BlockDevice => config.get_block_device_color(),
+            FileIcon::CharDevice => config.get_char_device_color(),
+            FileIcon::Fifo => config.get_fifo_color(),
+            FileIcon::Socket => config.get_socket_color(),
         }
     }
 }
+
+/// Built-in nerd-font icon for a directory with a well-known name. `name` must be lowercase.
+pub fn builtin_directory_icon(name: &str) -> Option<&'static str> {
+    let icon = match name {
+        ".git" => "\u{e5fb}",
+        ".github" => "\u{e5fd}",
+        ".config" => "\u{e5fc}",
+        ".vscode" => "\u{e70c}",
+        "node_modules" => "\u{e5fa}",
+        "src" | "lib" => "\u{f121}",
+        "bin" => "\u{f489}",
+        "doc" | "docs" => "\u{f02d}",
+        "test" | "tests" | "spec" => "\u{f0c3}",
+        "target" | "build" | "dist" | "out" => "\u{f487}",
+        _ => return None,
+    };
+
+    Some(icon)
+}
+
+/// Built-in nerd-font icon for a file with a well-known name. `name` must be lowercase.
+pub fn builtin_filename_icon(name: &str) -> Option<&'static str> {
+    let icon = match name {
+        "cargo.toml" | "cargo.lock" => "\u{e7a8}",
+        "package.json" | "package-lock.json" | ".npmrc" => "\u{e71e}",
+        "dockerfile" | "containerfile" | "docker-compose.yml" | "compose.yaml" => "\u{f308}",
+        "makefile" | "justfile" | "cmakelists.txt" => "\u{f0ad}",
+        "license" | "license.md" | "license.txt" | "copying" => "\u{e60a}",
+        "readme" | "readme.md" | "readme.txt" => "\u{f02d}",
+        ".gitignore" | ".gitattributes" | ".gitmodules" | ".gitconfig" => "\u{f1d3}",
+        ".bashrc" | ".bash_profile" | ".zshrc" | ".zprofile" | ".profile" => "\u{f489}",
+        ".editorconfig" | ".env" => "\u{e615}",
+        "go.mod" | "go.sum" => "\u{e627}",
+        "gemfile" | "gemfile.lock" | "rakefile" => "\u{e739}",
+        "flake.nix" | "flake.lock" => "\u{f313}",
+        _ => return None,
+    };
+
+    Some(icon)
+}
+
+/// Built-in nerd-font icon for a file extension (without the dot). `extension` must be lowercase.
+pub fn builtin_extension_icon(extension: &str) -> Option<&'static str> {
+    let icon = match extension {
+        "rs" => "\u{e7a8}",
+        "c" | "h" => "\u{e61e}",
+        "cc" | "cpp" | "cxx" | "hh" | "hpp" => "\u{e61d}",
+        "go" => "\u{e627}",
+        "py" | "pyi" => "\u{e606}",
+        "js" | "mjs" | "cjs" => "\u{e74e}",
+        "ts" | "mts" | "cts" => "\u{e628}",
+        "jsx" | "tsx" => "\u{e7ba}",
+        "java" | "jar" => "\u{e738}",
+        "kt" | "kts" => "\u{e634}",
+        "rb" => "\u{e739}",
+        "php" => "\u{e73d}",
+        "lua" => "\u{e620}",
+        "swift" => "\u{e755}",
+        "hs" => "\u{e777}",
+        "ex" | "exs" => "\u{e62d}",
+        "zig" => "\u{e6a9}",
+        "nix" => "\u{f313}",
+        "vim" => "\u{e62b}",
+        "sh" | "bash" | "zsh" | "fish" => "\u{f489}",
+        "html" | "htm" => "\u{e736}",
+        "css" => "\u{e749}",
+        "scss" | "sass" => "\u{e603}",
+        "json" | "jsonc" => "\u{e60b}",
+        "toml" | "yaml" | "yml" | "ini" | "cfg" | "conf" => "\u{e615}",
+        "xml" => "\u{e619}",
+        "md" | "markdown" => "\u{f48a}",
+        "txt" | "log" => "\u{f15c}",
+        "pdf" => "\u{f1c1}",
+        "doc" | "docx" | "odt" => "\u{f1c2}",
+        "xls" | "xlsx" | "ods" | "csv" | "tsv" => "\u{f1c3}",
+        "ppt" | "pptx" | "odp" => "\u{f1c4}",
+        "sql" | "db" | "sqlite" | "sqlite3" => "\u{f1c0}",
+        "lock" => "\u{f023}",
+        "diff" | "patch" => "\u{f440}",
+        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "svg" | "webp" | "ico" | "tiff" => "\u{f1c5}",
+        "mp3" | "flac" | "wav" | "ogg" | "m4a" | "opus" => "\u{f001}",
+        "mp4" | "mkv" | "avi" | "mov" | "webm" => "\u{f03d}",
+        "zip" | "tar" | "gz" | "tgz" | "xz" | "bz2" | "zst" | "7z" | "rar" | "deb" | "rpm" => {
+            "\u{f410}"
+        }
+        "iso" | "img" | "dmg" => "\u{f0a0}",
+        "ttf" | "otf" | "woff" | "woff2" => "\u{f031}",
+        _ => return None,
+    };
+
+    Some(icon)
+}
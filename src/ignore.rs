@@ -0,0 +1,283 @@
+/// Applies `.gitignore`-style rules: `.gitignore` files, `.git/info/exclude`
+/// and git's global excludes inside a repository, and `.ignore` files anywhere.
+use crate::git;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Ignore files read at each directory level, in increasing order of precedence.
+const IGNORE_FILE_NAMES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// The rules that apply to the entries of one directory.
+#[derive(Debug, Clone)]
+pub struct IgnoreRules {
+    directory: PathBuf,
+    in_repository: bool,
+    /// Rule files in increasing order of precedence
+    files: Vec<Arc<IgnoreFile>>,
+}
+
+/// The rules of one ignore file, matched relative to `base`.
+#[derive(Debug)]
+struct IgnoreFile {
+    base: PathBuf,
+    rules: Vec<IgnoreRule>,
+}
+
+#[derive(Debug)]
+struct IgnoreRule {
+    pattern: Vec<char>,
+    negated: bool,
+    directory_only: bool,
+    /// Matched against the whole path below the base rather than just the name
+    anchored: bool,
+}
+
+impl IgnoreRules {
+    /// Collects the rules for listing `directory`: git's global excludes, the
+    /// repository's `info/exclude`, and the ignore files from the repository
+    /// root (or the filesystem root outside a repository) down to `directory`.
+    pub fn for_directory(directory: &Path) -> Self {
+        let directory = directory
+            .canonicalize()
+            .unwrap_or_else(|_| directory.to_path_buf());
+        let repository_root = git::repository_root(&directory);
+
+        let mut rules = IgnoreRules {
+            directory: directory.clone(),
+            in_repository: repository_root.is_some(),
+            files: Vec::new(),
+        };
+
+        if let Some(root) = &repository_root {
+            if let Some(path) = global_excludes_file() {
+                rules.load(root, &path);
+            }
+            rules.load(root, &root.join(".git").join("info").join("exclude"));
+        }
+
+        let mut levels: Vec<&Path> = directory
+            .ancestors()
+            .take_while(|ancestor| {
+                repository_root
+                    .as_deref()
+                    .is_none_or(|root| ancestor.starts_with(root))
+            })
+            .collect();
+        levels.reverse();
+        for level in levels {
+            rules.load_directory(level);
+        }
+
+        rules
+    }
+
+    /// The rules for a subdirectory, adding the ignore files found in it.
+    pub fn child(&self, name: &str) -> Self {
+        let mut rules = self.clone();
+        rules.directory = self.directory.join(name);
+        let directory = rules.directory.clone();
+        rules.load_directory(&directory);
+        rules
+    }
+
+    /// Whether the entry `name` in this directory is ignored. The last rule
+    /// that matches decides, so later and deeper rules can re-include entries.
+    pub fn is_ignored(&self, name: &str, is_dir: bool) -> bool {
+        // Git's own metadata is never part of the working tree
+        if self.in_repository && name == ".git" {
+            return true;
+        }
+
+        let path = self.directory.join(name);
+        for file in self.files.iter().rev() {
+            let Ok(relative) = path.strip_prefix(&file.base) else {
+                continue;
+            };
+            let relative: Vec<char> = relative.to_string_lossy().chars().collect();
+            let name: Vec<char> = name.chars().collect();
+
+            if let Some(rule) = file
+                .rules
+                .iter()
+                .rev()
+                .find(|rule| rule.matches(&relative, &name, is_dir))
+            {
+                return !rule.negated;
+            }
+        }
+
+        false
+    }
+
+    fn load_directory(&mut self, directory: &Path) {
+        for file_name in IGNORE_FILE_NAMES {
+            if file_name == ".gitignore" && !self.in_repository {
+                continue;
+            }
+            self.load(directory, &directory.join(file_name));
+        }
+    }
+
+    fn load(&mut self, base: &Path, path: &Path) {
+        let Ok(contents) = fs::read_to_string(path) else {
+            return;
+        };
+
+        let rules: Vec<IgnoreRule> = contents.lines().filter_map(IgnoreRule::parse).collect();
+        if !rules.is_empty() {
+            self.files.push(Arc::new(IgnoreFile {
+                base: base.to_path_buf(),
+                rules,
+            }));
+        }
+    }
+}
+
+impl IgnoreRule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (directory_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        // A slash anywhere but the end ties the pattern to the ignore file's directory
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+
+        Some(IgnoreRule {
+            pattern: line.chars().collect(),
+            negated,
+            directory_only,
+            anchored,
+        })
+    }
+
+    fn matches(&self, relative: &[char], name: &[char], is_dir: bool) -> bool {
+        if self.directory_only && !is_dir {
+            return false;
+        }
+
+        if self.anchored {
+            glob_match(&self.pattern, relative)
+        } else {
+            glob_match(&self.pattern, name)
+        }
+    }
+}
+
+/// Matches gitignore globs: `*` and `?` stay within one path component, `**`
+/// spans components, and `[...]` matches a character class.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // `**/` matches zero or more whole directories
+            (0..=text.len())
+                .filter(|&index| index == 0 || text[index - 1] == '/')
+                .any(|index| glob_match(rest, &text[index..]))
+        }
+        ['*', '*'] => true,
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&index| index == 0 || text[index - 1] != '/')
+            .any(|index| glob_match(rest, &text[index..])),
+        ['?', rest @ ..] => {
+            matches!(text.first(), Some(&character) if character != '/')
+                && glob_match(rest, &text[1..])
+        }
+        ['[', rest @ ..] => match (text.first(), class_match(rest, text.first().copied())) {
+            (Some(_), Some((true, after))) => glob_match(after, &text[1..]),
+            // An unterminated class is a literal `[`
+            (Some('['), None) => glob_match(rest, &text[1..]),
+            _ => false,
+        },
+        ['\\', literal, rest @ ..] => text.first() == Some(literal) && glob_match(rest, &text[1..]),
+        [literal, rest @ ..] => text.first() == Some(literal) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Matches `character` against a class whose `[` has been consumed, returning
+/// whether it matched and the pattern after the closing `]`.
+fn class_match(pattern: &[char], character: Option<char>) -> Option<(bool, &[char])> {
+    let (negated, mut pattern) = match pattern {
+        ['!' | '^', rest @ ..] => (true, rest),
+        _ => (false, pattern),
+    };
+    let character = character?;
+    let mut matched = false;
+    let mut first = true;
+
+    loop {
+        match pattern {
+            [']', rest @ ..] if !first => return Some((matched != negated, rest)),
+            [start, '-', end, rest @ ..] if *end != ']' => {
+                matched |= (*start..=*end).contains(&character);
+                pattern = rest;
+            }
+            [literal, rest @ ..] => {
+                matched |= *literal == character;
+                pattern = rest;
+            }
+            [] => return None,
+        }
+        first = false;
+    }
+}
+
+/// Git's `core.excludesFile`, from the user's git config or its XDG default.
+fn global_excludes_file() -> Option<PathBuf> {
+    let home = dirs::home_dir();
+    let xdg_git = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| home.as_ref().map(|home| home.join(".config")))
+        .map(|config| config.join("git"));
+
+    // ~/.gitconfig is read after the XDG config, so it wins
+    let configured = [
+        xdg_git.as_ref().map(|git| git.join("config")),
+        home.as_ref().map(|home| home.join(".gitconfig")),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|path| fs::read_to_string(path).ok())
+    .filter_map(|contents| excludes_file_setting(&contents))
+    .next_back();
+
+    configured
+        .map(|path| match (path.strip_prefix("~/"), &home) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ => PathBuf::from(path),
+        })
+        .or_else(|| xdg_git.map(|git| git.join("ignore")))
+}
+
+/// The `excludesFile` value in a git config file's `[core]` section.
+fn excludes_file_setting(contents: &str) -> Option<String> {
+    let mut in_core = false;
+    let mut setting = None;
+
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_core = line.eq_ignore_ascii_case("[core]");
+        } else if let (true, Some((key, value))) = (in_core, line.split_once('=')) {
+            if key.trim().eq_ignore_ascii_case("excludesfile") {
+                setting = Some(value.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+
+    setting
+}
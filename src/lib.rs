@@ -1,16 +1,31 @@
+pub mod category;
 pub mod cli;
 pub mod config;
+pub mod error;
 pub mod file_entry;
 pub mod filter;
 pub mod formatter;
+pub mod git;
 pub mod icon;
+pub mod ignore;
+pub mod ls_colors;
 pub mod reader;
+pub mod report;
 pub mod sort;
+pub mod style;
+pub mod terminal;
+pub mod validate;
 
 pub use cli::Args;
 pub use config::{Config, IconConfig};
+pub use error::LxError;
 pub use file_entry::FileEntry;
-pub use formatter::{format_long, format_short, format_short_compact};
+pub use formatter::{
+    Formatter, FormatterRegistry, TreeOptions, format_json, format_json_tree, format_long,
+    format_one_per_line, format_recursive, format_short, format_short_compact, format_short_grid,
+    write_json, write_json_tree, write_long, write_one_per_line, write_recursive, write_short,
+    write_short_compact, write_short_grid,
+};
 pub use icon::FileIcon;
-pub use reader::{MetadataMode, read_directory_entries, read_target};
+pub use reader::{MetadataMode, Partial, read_directory_entries, read_target};
 pub use sort::{SortField, SortOptions, SortOrder, sort_default};
@@ -0,0 +1,138 @@
+/// Reads the colour tables `ls` honours: GNU `LS_COLORS` and BSD `LSCOLORS`.
+use crate::file_entry::{FileEntry, FileType};
+use crate::style::Style;
+use std::collections::HashMap;
+use std::env;
+
+/// File type keys in the order BSD `LSCOLORS` lists their colour pairs.
+const BSD_KEYS: [&str; 11] = [
+    "di", "ln", "so", "pi", "ex", "bd", "cd", "su", "sg", "tw", "ow",
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct LsColors {
+    /// Styles for two-letter file type keys such as `di` and `ex`
+    types: HashMap<String, Style>,
+    /// Lowercased `*suffix` patterns; later entries take precedence, as in GNU `ls`
+    suffixes: Vec<(String, Style)>,
+}
+
+impl LsColors {
+    /// Reads `LS_COLORS`, falling back to BSD `LSCOLORS` when it is not set.
+    pub fn from_env() -> Option<Self> {
+        if let Some(value) = env::var("LS_COLORS").ok().filter(|value| !value.is_empty()) {
+            return Some(Self::parse(&value));
+        }
+
+        env::var("LSCOLORS")
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map(|value| Self::parse_bsd(&value))
+    }
+
+    /// Parses GNU `key=sgr` pairs separated by colons, e.g. `di=01;34:*.tar=01;31`.
+    pub fn parse(value: &str) -> Self {
+        let mut colors = LsColors::default();
+
+        for (key, sgr) in value.split(':').filter_map(|pair| pair.split_once('=')) {
+            if let Some(suffix) = key.strip_prefix('*') {
+                colors
+                    .suffixes
+                    .push((suffix.to_lowercase(), Style::from_sgr(sgr)));
+            } else if key == "ln" && sgr == "target" {
+                // Colouring links like their target needs the target's type,
+                // so such links keep lx's own symlink colour instead
+                continue;
+            } else {
+                colors.types.insert(key.to_string(), Style::from_sgr(sgr));
+            }
+        }
+
+        colors
+    }
+
+    /// Parses BSD foreground/background letter pairs, e.g. `exfxcxdxbxegedabagacad`.
+    pub fn parse_bsd(value: &str) -> Self {
+        let mut colors = LsColors::default();
+        let letters: Vec<char> = value.chars().collect();
+
+        for (key, pair) in BSD_KEYS.iter().zip(letters.chunks(2)) {
+            let mut codes = Vec::new();
+            if let Some(&foreground) = pair.first() {
+                if foreground.is_ascii_uppercase() {
+                    codes.push("1".to_string());
+                }
+                codes.extend(bsd_color(foreground).map(|color| (30 + color).to_string()));
+            }
+            if let Some(&background) = pair.get(1) {
+                codes.extend(bsd_color(background).map(|color| (40 + color).to_string()));
+            }
+            colors
+                .types
+                .insert(key.to_string(), Style::from_sgr(&codes.join(";")));
+        }
+
+        colors
+    }
+
+    /// Picks the style `ls` would use for an entry, if the table defines one.
+    pub fn style_for(&self, entry: &FileEntry, name: &str) -> Option<Style> {
+        let sticky = entry.mode & 0o1000 != 0;
+        let other_writable = entry.mode & 0o002 != 0;
+
+        match entry.get_file_type() {
+            FileType::Symlink if entry.is_broken_symlink => self.lookup(&["or", "ln"]),
+            FileType::Symlink => self.lookup(&["ln"]),
+            FileType::Directory if sticky && other_writable => self.lookup(&["tw", "di"]),
+            FileType::Directory if other_writable => self.lookup(&["ow", "di"]),
+            FileType::Directory if sticky => self.lookup(&["st", "di"]),
+            FileType::Directory => self.lookup(&["di"]),
+            FileType::Fifo => self.lookup(&["pi"]),
+            FileType::Socket => self.lookup(&["so"]),
+            FileType::BlockDevice => self.lookup(&["bd"]),
+            FileType::CharDevice => self.lookup(&["cd"]),
+            FileType::Executable | FileType::RegularFile => {
+                let mut keys = Vec::new();
+                if entry.mode & 0o4000 != 0 {
+                    keys.push("su");
+                }
+                if entry.mode & 0o2000 != 0 {
+                    keys.push("sg");
+                }
+                if entry.is_executable {
+                    keys.push("ex");
+                }
+
+                // Like GNU `ls`, suffixes only colour files without a more specific type
+                self.lookup(&keys)
+                    .or_else(|| {
+                        (!entry.is_executable)
+                            .then(|| self.suffix_style(name))
+                            .flatten()
+                    })
+                    .or_else(|| self.lookup(&["fi"]))
+            }
+        }
+    }
+
+    fn lookup(&self, keys: &[&str]) -> Option<Style> {
+        keys.iter().find_map(|key| self.types.get(*key)).cloned()
+    }
+
+    fn suffix_style(&self, name: &str) -> Option<Style> {
+        let name = name.to_lowercase();
+        self.suffixes
+            .iter()
+            .rev()
+            .find(|(suffix, _)| name.ends_with(suffix.as_str()))
+            .map(|(_, style)| style.clone())
+    }
+}
+
+/// Maps a BSD colour letter to its ANSI colour index; `x` keeps the default.
+fn bsd_color(letter: char) -> Option<u8> {
+    match letter.to_ascii_lowercase() {
+        letter @ 'a'..='h' => Some(letter as u8 - b'a'),
+        _ => None,
+    }
+}
@@ -1,65 +1,403 @@
-use lx_cli::config::load_config;
-use lx_cli::filter::EntryFilter;
+use lx_cli::cli::OutputFormat;
+use lx_cli::config::{
+    Config, ShortLayout, check_config, config_files, explicit_config_path, get_config_path,
+    init_config, load_config,
+};
+use lx_cli::error::{LxError, Operation};
+use lx_cli::filter::{EntryFilter, check_pattern};
 use lx_cli::formatter::{
-    format_long, format_one_per_line, format_recursive, format_short, format_short_compact,
+    Formatter, FormatterRegistry, Listing, TreeOptions, write_json, write_json_node,
+    write_json_targets,
 };
-use lx_cli::{Args, MetadataMode, read_target};
-use std::io;
-use std::path::Path;
+use lx_cli::git::GitStatuses;
+use lx_cli::report::{self, report, report_minor};
+use lx_cli::sort::{DefaultSort, sort_entries};
+use lx_cli::{Args, FileEntry, MetadataMode, Partial, SortOptions, read_target};
+use std::env;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
 fn main() {
-    if let Err(error) = run() {
-        eprintln!("{}", error);
-        std::process::exit(1);
+    // Stdout is already line buffered, which keeps output on a terminal
+    // interleaved with errors; anything else gets one large buffer
+    let stdout = io::stdout();
+    let mut out: Box<dyn Write> = if stdout.is_terminal() {
+        Box::new(stdout.lock())
+    } else {
+        Box::new(BufWriter::new(stdout.lock()))
+    };
+
+    let result = run(&mut out).and_then(|()| out.flush().map_err(LxError::Output));
+    match result {
+        Ok(()) => std::process::exit(report::exit_status()),
+        // The reader went away, as with `lx | head`; exit as if killed by SIGPIPE
+        Err(error) if error.is_broken_pipe() => std::process::exit(error.exit_code()),
+        Err(error) => {
+            report(&error);
+            std::process::exit(report::exit_status());
+        }
     }
 }
 
-fn run() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse_args();
-    let config = load_config();
-    let sort = args.sort_options(config.display.default_sort());
-    let filter = EntryFilter::new(args.files, args.directories, args.exclude);
-
-    let target_path = Path::new(&args.target);
-
-    if !target_path.exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            format!(
-                "lx: cannot access '{}': No such file or directory",
-                args.target
-            ),
-        )
-        .into());
-    }
-
-    if args.recursive {
-        format_recursive(
-            target_path,
-            &config,
-            args.show_hidden,
-            args.long,
-            &filter,
-            sort,
-        )?;
-    } else {
-        let metadata_mode = if args.long || sort.requires_full_metadata() {
-            MetadataMode::Full
-        } else {
-            MetadataMode::Basic
+/// Lists every target to `out`, stopping early with an error for problems
+/// such as an invalid config. Problems with the targets themselves are
+/// reported through `report` and the rest still listed.
+fn run(out: &mut dyn Write) -> Result<(), LxError> {
+    let mut args = Args::parse_args();
+    let config_path = (!args.no_config).then(|| get_config_path(args.config.as_deref()));
+    if let (true, Some(path)) = (args.init_config, &config_path) {
+        return match init_config(path, args.force) {
+            Ok(()) => writeln!(out, "Wrote {}", path.display()).map_err(LxError::Output),
+            Err(error) => Err(LxError::config(
+                Some(path.clone()),
+                format!("cannot write config file '{}': {error}", path.display()),
+            )),
         };
-        let entries = filter.apply(read_target(target_path, args.show_hidden, metadata_mode)?);
-
-        if args.long {
-            format_long(entries, &config, sort);
-        } else if args.one_per_line {
-            format_one_per_line(entries, &config, sort);
-        } else if args.compact {
-            format_short_compact(entries, &config, sort);
+    }
+
+    // A file named explicitly must exist; the default one is optional
+    if let (false, Some(path)) = (args.no_config, explicit_config_path(args.config.as_deref())) {
+        if !path.exists() {
+            let message = format!(
+                "cannot access config file '{}': No such file or directory",
+                path.display()
+            );
+            return Err(LxError::config(Some(path), message));
+        }
+    }
+
+    // Each target is listed with the `.lx.toml` files of its own directory
+    let config_directories: Vec<PathBuf> = args
+        .targets
+        .iter()
+        .map(|target| config_directory(target))
+        .collect();
+    if args.check_config || args.config_path {
+        let mut files = Vec::new();
+        for directory in &config_directories {
+            for file in config_files(config_path.as_deref(), directory) {
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+        }
+        if args.check_config {
+            return match check_config(out, &files).map_err(LxError::Output)? {
+                true => Ok(()),
+                false => Err(LxError::config(None, "the config has problems")),
+            };
+        }
+        // Without a config file, show where `--init-config` would write one
+        if let Some(path) = config_path.as_deref().filter(|path| !path.exists()) {
+            writeln!(out, "{} (not found)", path.display()).map_err(LxError::Output)?;
+        }
+        for file in &files {
+            writeln!(out, "{}", file.display()).map_err(LxError::Output)?;
+        }
+        return Ok(());
+    }
+
+    let profile = args
+        .profile
+        .clone()
+        .or_else(|| env::var("LX_PROFILE").ok().filter(|name| !name.is_empty()));
+    // The first target's config decides what `--show-config` prints and
+    // which flags a profile adds
+    let config = load_config(
+        config_path.as_deref(),
+        &config_directories[0],
+        profile.as_deref(),
+    )?;
+    if args.show_config {
+        let contents = toml::to_string(&config)
+            .map_err(|error| LxError::config(None, format!("cannot show config: {error}")))?;
+        return write!(out, "{contents}").map_err(LxError::Output);
+    }
+    if let Some(name) = &profile {
+        let defaults = config.profile_args(name);
+        if !defaults.is_empty() {
+            args = Args::parse_args_with_defaults(&defaults);
+        }
+    }
+
+    args.recursive |= args.level.is_some();
+    for pattern in &args.exclude {
+        check_pattern(pattern)?;
+    }
+    let formatters = FormatterRegistry::default();
+    // Targets whose directories share the same config files share a context
+    let mut contexts = vec![(
+        config_files(config_path.as_deref(), &config_directories[0]),
+        Context::new(&args, config, &formatters)?,
+    )];
+    let mut target_contexts = Vec::with_capacity(args.targets.len());
+    for directory in &config_directories {
+        let files = config_files(config_path.as_deref(), directory);
+        let index = match contexts.iter().position(|(known, _)| *known == files) {
+            Some(index) => index,
+            None => {
+                let config = load_config(config_path.as_deref(), directory, profile.as_deref())?;
+                contexts.push((files, Context::new(&args, config, &formatters)?));
+                contexts.len() - 1
+            }
+        };
+        target_contexts.push(index);
+    }
+    let git_statuses = GitStatuses::new();
+
+    let mut files: Vec<(usize, Vec<FileEntry>)> = Vec::new();
+    let mut directories = Vec::new();
+    // Several targets make one JSON document keyed by target rather than
+    // one document each
+    let json_targets = args.output != OutputFormat::Text && args.targets.len() > 1;
+    let mut json_listings = Vec::new();
+
+    // Like `ls`, files named on the command line are listed together first,
+    // followed by each directory under its own header
+    for (target, &index) in args.targets.iter().zip(&target_contexts) {
+        let target_path = Path::new(target);
+        let context = &contexts[index].1;
+
+        // `exists` follows symlinks, which would reject a dangling link named directly
+        if let Err(error) = target_path.symlink_metadata() {
+            report(&LxError::io(target, Operation::Access, error));
+        } else if target_path.is_dir() {
+            directories.push((target, context));
         } else {
-            format_short(entries, &config, sort);
+            let metadata_mode = context.metadata_mode(&args);
+            let mut entries = match read_target(target_path, args.show_hidden, metadata_mode) {
+                Ok(entries) => report_problems(entries),
+                Err(error) => {
+                    report(&LxError::io(target, Operation::Access, error));
+                    continue;
+                }
+            };
+            for entry in &mut entries {
+                entry.path = target.into();
+            }
+            let mut entries = context.filter.apply(entries);
+            if context.shows_git_status {
+                git_statuses.annotate(Path::new(""), &mut entries);
+            }
+            if json_targets {
+                sort_entries(&mut entries, context.sort);
+                json_listings.push((target.clone(), Listing::Entries(entries)));
+            } else {
+                // Files under different configs are listed one group after another
+                match files.iter_mut().find(|(known, _)| *known == index) {
+                    Some((_, group)) => group.extend(entries),
+                    None => files.push((index, entries)),
+                }
+            }
         }
     }
 
+    let show_headers = args.targets.len() > 1 && args.output == OutputFormat::Text;
+    let mut has_previous_section = false;
+    for (index, entries) in files {
+        if !entries.is_empty() {
+            let listing = Listing::Entries(entries);
+            write_listing(out, listing, &args, &contexts[index].1).map_err(LxError::Output)?;
+            has_previous_section = true;
+        }
+    }
+
+    for (directory, context) in directories {
+        if show_headers {
+            if has_previous_section {
+                writeln!(out).map_err(LxError::Output)?;
+            }
+            writeln!(out, "{directory}:").map_err(LxError::Output)?;
+        }
+        has_previous_section = true;
+
+        let git_statuses = context.shows_git_status.then_some(&git_statuses);
+        match read_directory(Path::new(directory), &args, context, git_statuses) {
+            Err(error) => report(&error),
+            Ok(Some(mut listing)) if json_targets => {
+                if let Listing::Entries(entries) = &mut listing {
+                    sort_entries(entries, context.sort);
+                }
+                json_listings.push((directory.clone(), listing));
+            }
+            Ok(Some(listing)) => {
+                write_listing(out, listing, &args, context).map_err(LxError::Output)?;
+            }
+            Ok(None) => {}
+        }
+    }
+
+    if json_targets {
+        let ndjson = args.output == OutputFormat::Ndjson;
+        write_json_targets(out, json_listings, ndjson).map_err(LxError::Output)?;
+    }
+
     Ok(())
 }
+
+/// How targets are listed under one set of config files.
+struct Context<'a> {
+    config: Config,
+    formatter: &'a dyn Formatter,
+    recursive: bool,
+    filter: EntryFilter,
+    sort: SortOptions,
+    shows_git_status: bool,
+    color: bool,
+}
+
+impl<'a> Context<'a> {
+    /// Applies the command line to `config`, which flags take precedence over.
+    fn new(
+        args: &Args,
+        mut config: Config,
+        formatters: &'a FormatterRegistry,
+    ) -> Result<Self, LxError> {
+        if args.grid {
+            config.display.layout = ShortLayout::Grid;
+        }
+        if args.level.is_some() {
+            config.display.tree.max_depth = args.level;
+        }
+        let format = args.format_name().unwrap_or(&config.display.format);
+        let Some(formatter) = formatters.get(format) else {
+            let message = format!(
+                "unknown format '{format}' (expected one of: {})",
+                formatters.names().join(", ")
+            );
+            return Err(LxError::config(None, message));
+        };
+        if !args.icons.unwrap_or(config.display.icons).use_icons() {
+            config.icons.clear();
+        }
+        if args.git {
+            config.display.git_status = true;
+            let fields = &mut config.display.long_format_fields;
+            if !fields.iter().any(|field| field == "git") {
+                // Put the column just before the name, ahead of any icon
+                let position = fields
+                    .iter()
+                    .position(|field| field == "icon" || field == "filename")
+                    .unwrap_or(fields.len());
+                fields.insert(position, "git".to_string());
+            }
+        }
+        // Config patterns are only warned about when the config is loaded, so
+        // a bad one does not stop every listing
+        let mut exclude = config.display.exclude.clone();
+        exclude.extend(args.exclude.iter().cloned());
+        let git_ignore = if args.git_ignore || args.no_git_ignore {
+            args.git_ignore
+        } else {
+            config.display.git_ignore
+        };
+
+        Ok(Self {
+            formatter,
+            recursive: args.recursive || formatter.is_recursive(),
+            filter: EntryFilter::new(args.files, args.directories, exclude)
+                .with_git_ignore(git_ignore),
+            sort: args.sort_options(config.display.default_sort()),
+            shows_git_status: config
+                .display
+                .shows_git_status(formatter.uses_long_format_fields()),
+            color: args.color.unwrap_or(config.display.color).use_color(),
+            config,
+        })
+    }
+
+    /// Long, JSON and sorted listings need owners and groups, which are slower to read.
+    fn metadata_mode(&self, args: &Args) -> MetadataMode {
+        if self.formatter.needs_full_metadata()
+            || args.output != OutputFormat::Text
+            || self.sort.requires_full_metadata()
+        {
+            MetadataMode::Full
+        } else {
+            MetadataMode::Basic
+        }
+    }
+}
+
+/// Where `.lx.toml` lookup starts for `target`: the target itself, or the
+/// directory holding it.
+fn config_directory(target: &str) -> PathBuf {
+    let target = Path::new(target);
+    if target.is_dir() {
+        return target.to_path_buf();
+    }
+
+    match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Reads one directory's entries, or its tree when listing recursively. Gives
+/// `None` when the directory itself is filtered out of its tree.
+fn read_directory(
+    path: &Path,
+    args: &Args,
+    context: &Context,
+    git_statuses: Option<&GitStatuses>,
+) -> Result<Option<Listing>, LxError> {
+    let metadata_mode = context.metadata_mode(args);
+    let read_error = |error| LxError::io(path, Operation::OpenDirectory, error);
+    let options = TreeOptions {
+        show_hidden: args.show_hidden,
+        git_statuses,
+        max_depth: context.config.display.tree.max_depth,
+        ..TreeOptions::new(&context.filter, context.sort)
+    };
+
+    if context.recursive {
+        // JSON trees keep the long format's order whatever the formatter
+        let default_sort = match args.output {
+            OutputFormat::Text => context.formatter.tree_sort(),
+            OutputFormat::Json | OutputFormat::Ndjson => DefaultSort::TypeThenName,
+        };
+        let tree = options
+            .renderer(default_sort)
+            .collect_tree(path, options.show_hidden, metadata_mode)
+            .map_err(read_error)?;
+        return Ok(report_problems(tree).map(Listing::Tree));
+    }
+
+    let entries = read_target(path, options.show_hidden, metadata_mode).map_err(read_error)?;
+    let mut entries = options
+        .filter
+        .apply_in_directory(path, report_problems(entries));
+    if let Some(git_statuses) = options.git_statuses {
+        git_statuses.annotate(path, &mut entries);
+    }
+    Ok(Some(Listing::Entries(entries)))
+}
+
+/// Reports the problems that left entries out of a listing, which only raise
+/// the exit status to 1, and gives back what was read.
+fn report_problems<T>(partial: Partial<T>) -> T {
+    for problem in &partial.problems {
+        report_minor(problem);
+    }
+    partial.value
+}
+
+fn write_listing(
+    out: &mut dyn Write,
+    listing: Listing,
+    args: &Args,
+    context: &Context,
+) -> io::Result<()> {
+    colored::control::set_override(context.color);
+    let (formatter, config, sort) = (context.formatter, &context.config, context.sort);
+    match (listing, args.output) {
+        (Listing::Entries(entries), OutputFormat::Text) => {
+            formatter.write_entries(out, entries, config, sort)
+        }
+        (Listing::Entries(entries), OutputFormat::Json) => write_json(out, entries, sort, false),
+        (Listing::Entries(entries), OutputFormat::Ndjson) => write_json(out, entries, sort, true),
+        (Listing::Tree(tree), OutputFormat::Text) => formatter.write_tree(out, tree, config),
+        (Listing::Tree(tree), OutputFormat::Json) => write_json_node(out, &tree, false),
+        (Listing::Tree(tree), OutputFormat::Ndjson) => write_json_node(out, &tree, true),
+    }
+}
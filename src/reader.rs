@@ -1,9 +1,10 @@
 /// Handles directory traversal and gathering file metadata.
-use crate::file_entry::FileEntry;
+use crate::error::{LxError, Operation};
+use crate::file_entry::{FileEntry, SpecialFile};
 use std::collections::HashMap;
 use std::fs;
 use std::io;
-use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -18,44 +19,79 @@ pub struct DiscoveredEntry {
     pub full_path: PathBuf,
 }
 
+/// What was read, together with the problems that left some of it out
+/// without stopping the rest, such as entries that vanished while being read.
+/// Reporting them is up to the caller.
+#[derive(Debug)]
+pub struct Partial<T> {
+    pub value: T,
+    pub problems: Vec<LxError>,
+}
+
+impl<T> Partial<T> {
+    /// A value read without problems.
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            problems: Vec::new(),
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Partial<U> {
+        Partial {
+            value: f(self.value),
+            problems: self.problems,
+        }
+    }
+}
+
 #[derive(Default)]
 struct UserGroupCache {
     users: HashMap<u32, String>,
     groups: HashMap<u32, String>,
 }
 
+/// Reads the entries of a directory, or a file named directly as a one-entry listing.
 pub fn read_target(
     target_path: &Path,
     show_hidden: bool,
     metadata_mode: MetadataMode,
-) -> io::Result<Vec<FileEntry>> {
+) -> io::Result<Partial<Vec<FileEntry>>> {
     if target_path.is_dir() {
-        return Ok(
-            read_directory_entries(target_path, show_hidden, metadata_mode)?
-                .into_iter()
-                .map(|entry| entry.entry)
-                .collect(),
-        );
+        let entries = read_directory_entries(target_path, show_hidden, metadata_mode)?;
+        return Ok(entries.map(|entries| entries.into_iter().map(|entry| entry.entry).collect()));
     }
 
+    // Like `ls`, a symlink named directly on the command line is listed as the link itself
     let mut cache = UserGroupCache::default();
-    Ok(vec![read_file_entry(
+    let metadata = fs::symlink_metadata(target_path)?;
+    Ok(Partial::new(vec![read_file_entry(
         target_path,
+        &metadata,
         metadata_mode,
         &mut cache,
-    )?])
+    )]))
 }
 
+/// Reads the entries of a directory, leaving out those that cannot be read
+/// and returning why alongside the rest.
 pub fn read_directory_entries(
     target_path: &Path,
     show_hidden: bool,
     metadata_mode: MetadataMode,
-) -> io::Result<Vec<DiscoveredEntry>> {
+) -> io::Result<Partial<Vec<DiscoveredEntry>>> {
     let mut entries = Vec::new();
+    let mut problems = Vec::new();
     let mut cache = UserGroupCache::default();
 
     for entry in fs::read_dir(target_path)? {
-        let entry = entry?;
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                problems.push(LxError::io(target_path, Operation::Access, error));
+                continue;
+            }
+        };
         let file_name = entry.file_name();
 
         if !show_hidden && is_hidden(&file_name) {
@@ -63,41 +99,69 @@ pub fn read_directory_entries(
         }
 
         let full_path = entry.path();
-        let metadata = entry.metadata()?;
-        let entry = build_file_entry(file_name, &metadata, metadata_mode, &mut cache);
+        // Entries can vanish or be unreadable, as often happens in /proc or
+        // busy build directories; they are left out and the rest listed
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                problems.push(LxError::io(&full_path, Operation::Access, error));
+                continue;
+            }
+        };
+        let entry = build_file_entry(file_name, &full_path, &metadata, metadata_mode, &mut cache);
 
         entries.push(DiscoveredEntry { entry, full_path });
     }
 
-    Ok(entries)
+    Ok(Partial {
+        value: entries,
+        problems,
+    })
 }
 
+/// Reads a single entry, following symlinks so a linked tree root is shown as a directory.
 pub(crate) fn read_entry(target_path: &Path, metadata_mode: MetadataMode) -> io::Result<FileEntry> {
     let mut cache = UserGroupCache::default();
-    read_file_entry(target_path, metadata_mode, &mut cache)
+    let metadata = fs::metadata(target_path)?;
+    Ok(read_file_entry(
+        target_path,
+        &metadata,
+        metadata_mode,
+        &mut cache,
+    ))
 }
 
 fn read_file_entry(
     target_path: &Path,
+    metadata: &fs::Metadata,
     metadata_mode: MetadataMode,
     cache: &mut UserGroupCache,
-) -> io::Result<FileEntry> {
-    let metadata = fs::metadata(target_path)?;
+) -> FileEntry {
     let file_name = target_path
         .file_name()
         .unwrap_or(target_path.as_os_str())
         .to_os_string();
 
-    Ok(build_file_entry(file_name, &metadata, metadata_mode, cache))
+    build_file_entry(file_name, target_path, metadata, metadata_mode, cache)
 }
 
 fn build_file_entry(
     path: std::ffi::OsString,
+    full_path: &Path,
     metadata: &fs::Metadata,
     metadata_mode: MetadataMode,
     cache: &mut UserGroupCache,
 ) -> FileEntry {
     let is_dir = metadata.is_dir();
+    let is_symlink = metadata.file_type().is_symlink();
+    let symlink_target = if is_symlink {
+        fs::read_link(full_path).ok()
+    } else {
+        None
+    };
+    // `Path::exists` follows the link, so it is false for dangling and looping links
+    let is_broken_symlink = is_symlink && !full_path.exists();
+    let special_file = special_file_type(&metadata.file_type());
     let mode = metadata.permissions().mode();
     let (owner, group) = match metadata_mode {
         MetadataMode::Basic => (String::new(), String::new()),
@@ -111,7 +175,7 @@ fn build_file_entry(
         is_hidden: is_hidden(&path),
         path,
         is_dir,
-        is_executable: !is_dir && (mode & 0o111) != 0,
+        is_executable: metadata.is_file() && (mode & 0o111) != 0,
         mode,
         size: metadata.len(),
         modified: metadata
@@ -119,7 +183,29 @@ fn build_file_entry(
             .unwrap_or(std::time::SystemTime::UNIX_EPOCH),
         owner,
         group,
+        uid: metadata.uid(),
+        gid: metadata.gid(),
         nlink: metadata.nlink(),
+        is_symlink,
+        symlink_target,
+        is_broken_symlink,
+        special_file,
+        rdev: metadata.rdev(),
+        git_status: None,
+    }
+}
+
+fn special_file_type(file_type: &fs::FileType) -> Option<SpecialFile> {
+    if file_type.is_block_device() {
+        Some(SpecialFile::BlockDevice)
+    } else if file_type.is_char_device() {
+        Some(SpecialFile::CharDevice)
+    } else if file_type.is_fifo() {
+        Some(SpecialFile::Fifo)
+    } else if file_type.is_socket() {
+        Some(SpecialFile::Socket)
+    } else {
+        None
     }
 }
 
@@ -154,15 +240,15 @@ fn lookup_username(uid: u32) -> String {
     // Try to get username from system, fallback to uid
     #[cfg(unix)]
     {
-        use std::ffi::CStr;
-        unsafe {
-            let passwd = libc::getpwuid(uid);
-            if !passwd.is_null() {
-                let name = CStr::from_ptr((*passwd).pw_name);
-                if let Ok(name_str) = name.to_str() {
-                    return name_str.to_string();
-                }
-            }
+        // SAFETY: `lookup_name` passes a record, buffer and result to fill in
+        let name = lookup_name(
+            |passwd, buffer, result| unsafe {
+                libc::getpwuid_r(uid, passwd, buffer.as_mut_ptr(), buffer.len(), result)
+            },
+            |passwd: &libc::passwd| passwd.pw_name,
+        );
+        if let Some(name) = name {
+            return name;
         }
     }
     uid.to_string()
@@ -172,16 +258,52 @@ fn lookup_groupname(gid: u32) -> String {
     // Try to get group name from system, fallback to gid
     #[cfg(unix)]
     {
-        use std::ffi::CStr;
-        unsafe {
-            let group = libc::getgrgid(gid);
-            if !group.is_null() {
-                let name = CStr::from_ptr((*group).gr_name);
-                if let Ok(name_str) = name.to_str() {
-                    return name_str.to_string();
-                }
-            }
+        // SAFETY: as for `lookup_username`
+        let name = lookup_name(
+            |group, buffer, result| unsafe {
+                libc::getgrgid_r(gid, group, buffer.as_mut_ptr(), buffer.len(), result)
+            },
+            |group: &libc::group| group.gr_name,
+        );
+        if let Some(name) = name {
+            return name;
         }
     }
     gid.to_string()
 }
+
+/// The largest buffer a user or group record is looked up with.
+#[cfg(unix)]
+const MAX_LOOKUP_BUFFER: usize = 1 << 20;
+
+/// Runs a reentrant `getpwuid_r`-style `lookup` with a record and buffer of
+/// its own, so tree workers can look names up at once, growing the buffer
+/// while the record does not fit. `None` when there is no such id.
+#[cfg(unix)]
+fn lookup_name<T>(
+    lookup: impl Fn(*mut T, &mut [libc::c_char], *mut *mut T) -> libc::c_int,
+    name: impl Fn(&T) -> *const libc::c_char,
+) -> Option<String> {
+    use std::ffi::CStr;
+    use std::mem::MaybeUninit;
+    use std::ptr;
+
+    let mut buffer: Vec<libc::c_char> = vec![0; 1024];
+    loop {
+        let mut record = MaybeUninit::<T>::uninit();
+        let mut result = ptr::null_mut();
+        let status = lookup(record.as_mut_ptr(), &mut buffer, &mut result);
+        if status == libc::ERANGE && buffer.len() < MAX_LOOKUP_BUFFER {
+            buffer.resize(buffer.len() * 2, 0);
+            continue;
+        }
+        if status != 0 || result.is_null() {
+            return None;
+        }
+
+        // SAFETY: a successful lookup points `result` at the filled-in
+        // record, whose strings live in `buffer`
+        let name = unsafe { CStr::from_ptr(name(&*result)) };
+        return name.to_str().ok().map(str::to_string);
+    }
+}
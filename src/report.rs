@@ -0,0 +1,39 @@
+/// Reports problems met while listing on stderr and tracks the exit status
+/// they call for, so one bad entry does not stop the rest of the listing.
+use crate::error::LxError;
+use std::io;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// The highest exit status of the problems reported so far.
+static EXIT_STATUS: AtomicI32 = AtomicI32::new(0);
+
+/// The exit status for problems inside a listing, as with GNU `ls`.
+const MINOR_EXIT_STATUS: i32 = 1;
+
+/// Prints `lx: {error}` on stderr and raises the exit status to the error's own.
+pub fn report(error: &LxError) {
+    eprintln!("lx: {error}");
+    EXIT_STATUS.fetch_max(error.exit_code(), Ordering::Relaxed);
+}
+
+/// Like `report`, for a problem inside a listing such as an unreadable
+/// subdirectory, which only raises the exit status to 1.
+pub fn report_minor(error: &LxError) {
+    eprintln!("lx: {error}");
+    EXIT_STATUS.fetch_max(MINOR_EXIT_STATUS, Ordering::Relaxed);
+}
+
+/// The exit status for the problems reported so far: 0 if there were none.
+pub fn exit_status() -> i32 {
+    EXIT_STATUS.load(Ordering::Relaxed)
+}
+
+/// An I/O error as `ls` words it, such as `Permission denied`, without the
+/// `(os error 13)` suffix Rust adds.
+pub fn error_message(error: &io::Error) -> String {
+    let message = error.to_string();
+    match message.rfind(" (os error ") {
+        Some(index) if error.raw_os_error().is_some() => message[..index].to_string(),
+        _ => message,
+    }
+}
@@ -0,0 +1,219 @@
+/// Terminal text styles kept as raw SGR parameters, so styles read from
+/// `LS_COLORS` can be reproduced exactly alongside lx's own colours.
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Style {
+    /// Parameters between `ESC[` and `m`, e.g. `01;34`; empty means unstyled
+    sgr: String,
+}
+
+impl Style {
+    /// A style from SGR parameters as written in `LS_COLORS`, e.g. `01;34` or `38;5;208`.
+    pub fn from_sgr(sgr: &str) -> Self {
+        let sgr = sgr.trim().trim_matches(';');
+        // `0`/`00` is a plain reset, which lx shows as no style at all
+        if sgr
+            .split(';')
+            .all(|code| code.trim_start_matches('0').is_empty())
+        {
+            return Style::default();
+        }
+
+        Style {
+            sgr: sgr.to_string(),
+        }
+    }
+
+    /// Parses a config style: attributes, a foreground colour and an optional
+    /// `on <colour>` background, e.g. `bold #ff8800 on #202020`.
+    pub fn parse(style: &str) -> Result<Self, String> {
+        let mut attributes = Vec::new();
+        let mut foreground = None;
+        let mut background = None;
+
+        let words = without_spaces_in_parentheses(style).to_lowercase();
+        let mut words = words.split_whitespace();
+        while let Some(word) = words.next() {
+            if word == "on" {
+                let color = words
+                    .next()
+                    .ok_or_else(|| "expected a colour after `on`".to_string())?;
+                background = color_code(color, 40)?;
+            } else if let Some(code) = attribute_code(word) {
+                attributes.push(code.to_string());
+            } else {
+                foreground = color_code(word, 30)?;
+            }
+        }
+
+        let codes: Vec<String> = attributes
+            .into_iter()
+            .chain(foreground)
+            .chain(background)
+            .collect();
+        Ok(Style {
+            sgr: codes.join(";"),
+        })
+    }
+
+    pub fn bold(self) -> Self {
+        let sgr = if self.sgr.is_empty() {
+            "1".to_string()
+        } else if self.sgr == "1" || self.sgr.starts_with("1;") {
+            self.sgr
+        } else {
+            format!("1;{}", self.sgr)
+        };
+        Style { sgr }
+    }
+
+    pub fn is_plain(&self) -> bool {
+        self.sgr.is_empty()
+    }
+
+    /// Wraps `text` in the style, honouring the `--color` policy like `colored` does.
+    pub fn paint(&self, text: &str) -> String {
+        if self.is_plain() || !colored::control::SHOULD_COLORIZE.should_colorize() {
+            return text.to_string();
+        }
+
+        format!("\x1b[{}m{}\x1b[0m", self.sgr, text)
+    }
+}
+
+/// The 8 base colour names, indexed like their ANSI codes; `bright_` forms
+/// are built from them.
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// Attributes, named colours and other words a style may contain, for suggestions.
+pub(crate) fn keywords() -> Vec<String> {
+    let attributes = [
+        "bold",
+        "dim",
+        "italic",
+        "underline",
+        "blink",
+        "reverse",
+        "hidden",
+        "strikethrough",
+        "on",
+        "default",
+    ];
+
+    attributes
+        .iter()
+        .map(|word| word.to_string())
+        .chain(COLOR_NAMES.iter().map(|name| name.to_string()))
+        .chain(COLOR_NAMES.iter().map(|name| format!("bright_{name}")))
+        .collect()
+}
+
+/// Words in a style that are neither keywords nor hex, `rgb()` or `ansi()` colours.
+pub(crate) fn unknown_words(style: &str) -> Vec<String> {
+    let keywords = keywords();
+    without_spaces_in_parentheses(style)
+        .to_lowercase()
+        .split_whitespace()
+        .filter(|word| {
+            !keywords.iter().any(|keyword| keyword == word)
+                && !["#", "rgb(", "ansi("]
+                    .iter()
+                    .any(|prefix| word.starts_with(prefix))
+                && !matches!(*word, "dimmed" | "reversed")
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+fn attribute_code(word: &str) -> Option<u8> {
+    match word {
+        "bold" => Some(1),
+        "dim" | "dimmed" => Some(2),
+        "italic" => Some(3),
+        "underline" => Some(4),
+        "blink" => Some(5),
+        "reverse" | "reversed" => Some(7),
+        "hidden" => Some(8),
+        "strikethrough" => Some(9),
+        _ => None,
+    }
+}
+
+/// The SGR codes for a colour, where `base` is 30 for foregrounds and 40 for
+/// backgrounds; `default` keeps the terminal's own colour.
+fn color_code(color: &str, base: u8) -> Result<Option<String>, String> {
+    let extended = base + 8;
+
+    if color == "default" {
+        return Ok(None);
+    }
+    if let Some(index) = COLOR_NAMES.iter().position(|name| *name == color) {
+        return Ok(Some((base as usize + index).to_string()));
+    }
+    let bright_index = color
+        .strip_prefix("bright_")
+        .and_then(|name| COLOR_NAMES.iter().position(|candidate| *candidate == name));
+    if let Some(index) = bright_index {
+        return Ok(Some((base as usize + 60 + index).to_string()));
+    }
+    if let Some(hex) = color.strip_prefix('#') {
+        let [r, g, b] = parse_hex(hex).ok_or_else(|| format!("invalid hex colour `{color}`"))?;
+        return Ok(Some(format!("{extended};2;{r};{g};{b}")));
+    }
+    if let Some(components) = function_argument(color, "rgb") {
+        let [r, g, b] = parse_rgb(components)
+            .ok_or_else(|| format!("`{color}` needs three components from 0 to 255"))?;
+        return Ok(Some(format!("{extended};2;{r};{g};{b}")));
+    }
+    if let Some(index) = function_argument(color, "ansi") {
+        let index: u8 = index
+            .parse()
+            .map_err(|_| format!("`{color}` needs a colour number from 0 to 255"))?;
+        return Ok(Some(format!("{extended};5;{index}")));
+    }
+
+    Err(format!("unknown colour `{color}`"))
+}
+
+/// The text between `name(` and `)`, e.g. `12,34,56` for `rgb(12,34,56)`.
+fn function_argument<'a>(color: &'a str, name: &str) -> Option<&'a str> {
+    color
+        .strip_prefix(name)?
+        .strip_prefix('(')?
+        .strip_suffix(')')
+}
+
+fn parse_hex(hex: &str) -> Option<[u8; 3]> {
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+
+    let component = |start: usize| u8::from_str_radix(&hex[start..start + 2], 16).ok();
+    Some([component(0)?, component(2)?, component(4)?])
+}
+
+fn parse_rgb(components: &str) -> Option<[u8; 3]> {
+    let components: Vec<u8> = components
+        .split(',')
+        .map(|component| component.parse().ok())
+        .collect::<Option<_>>()?;
+    components.try_into().ok()
+}
+
+/// Lets `rgb(255, 136, 0)` be written with spaces without splitting it into words.
+fn without_spaces_in_parentheses(style: &str) -> String {
+    let mut depth = 0usize;
+    style
+        .chars()
+        .filter(|&character| {
+            match character {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            !(depth > 0 && character.is_whitespace())
+        })
+        .collect()
+}
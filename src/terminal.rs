@@ -0,0 +1,79 @@
+/// Queries properties of the terminal that lx is writing to.
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io::{self, IsTerminal};
+
+const DEFAULT_WIDTH: usize = 80;
+
+/// Returns the width of the terminal attached to stdout, falling back to the
+/// `COLUMNS` environment variable and then to 80 columns when output is redirected.
+pub fn terminal_width() -> usize {
+    stdout_window_width()
+        .or_else(|| {
+            env::var("COLUMNS")
+                .ok()
+                .and_then(|columns| columns.trim().parse().ok())
+                .filter(|&columns| columns > 0)
+        })
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+fn stdout_window_width() -> Option<usize> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only writes into the provided `winsize` struct
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+
+    (result == 0 && size.ws_col > 0).then_some(usize::from(size.ws_col))
+}
+
+/// When to use colours or icons: `auto` enables them only when writing to a terminal.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum When {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl When {
+    /// Resolves whether to colour output. In `auto` mode `CLICOLOR_FORCE` forces
+    /// colour on, while `NO_COLOR` or `CLICOLOR=0` turn it off for terminals too.
+    pub fn use_color(self) -> bool {
+        match self {
+            When::Always => true,
+            When::Never => false,
+            When::Auto => {
+                if env_flag("CLICOLOR_FORCE").is_some_and(|value| value != "0") {
+                    true
+                } else if env_flag("NO_COLOR").is_some()
+                    || env_flag("CLICOLOR").is_some_and(|value| value == "0")
+                {
+                    false
+                } else {
+                    io::stdout().is_terminal()
+                }
+            }
+        }
+    }
+
+    /// Resolves whether to show icons, which only depends on stdout being a terminal.
+    pub fn use_icons(self) -> bool {
+        match self {
+            When::Always => true,
+            When::Never => false,
+            When::Auto => io::stdout().is_terminal(),
+        }
+    }
+}
+
+/// Reads an environment variable, treating an empty value as unset.
+fn env_flag(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
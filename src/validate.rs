@@ -0,0 +1,399 @@
+/// Strict checks for config files, reporting each problem with its location.
+use crate::cli::Args;
+use crate::config::{Config, LONG_FORMAT_FIELDS, TREE_STYLES};
+use crate::filter::pattern_problem;
+use crate::formatter::FormatterRegistry;
+use crate::style::{self, Style};
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml::Spanned;
+use toml::de::{DeTable, DeValue};
+
+/// One problem in a config file, displayed as `path:line:column: message`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    /// Whether lx refuses to load the file, as for a style it cannot show,
+    /// rather than warning and carrying on
+    pub is_error: bool,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.path.display(),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
+
+/// What a config key holds, and so how its value is checked.
+#[derive(Clone, Copy)]
+enum Kind {
+    /// Anything; deserializing the `Config` checks its type
+    Value,
+    /// A colour or style such as `bold blue`
+    Style,
+    Table(&'static [(&'static str, Kind)]),
+    /// A table of user-chosen keys whose values are styles
+    StyleMap,
+    /// A table of user-chosen keys whose values are checked when deserializing
+    ValueMap,
+    /// The `[[colors.rules]]` array of tables
+    Rules,
+    /// `[profiles.<name>]` tables
+    Profiles,
+    LongFormatFields,
+    TreeStyle,
+    /// The name of a built-in formatter
+    Format,
+    /// Exclude patterns, which match names rather than paths
+    ExcludePatterns,
+    /// A profile's default command-line flags
+    ProfileArgs,
+}
+
+const ROOT_KEYS: &[(&str, Kind)] = &[
+    ("colors", Kind::Table(COLOR_KEYS)),
+    ("icons", Kind::Table(ICON_KEYS)),
+    ("display", Kind::Table(DISPLAY_KEYS)),
+    ("profiles", Kind::Profiles),
+];
+
+const PROFILE_KEYS: &[(&str, Kind)] = &[
+    ("args", Kind::ProfileArgs),
+    ("colors", Kind::Table(COLOR_KEYS)),
+    ("icons", Kind::Table(ICON_KEYS)),
+    ("display", Kind::Table(DISPLAY_KEYS)),
+];
+
+const COLOR_KEYS: &[(&str, Kind)] = &[
+    ("directory", Kind::Style),
+    ("executable", Kind::Style),
+    ("regular", Kind::Style),
+    ("hidden", Kind::Style),
+    ("symlink", Kind::Style),
+    ("broken_symlink", Kind::Style),
+    ("block_device", Kind::Style),
+    ("char_device", Kind::Style),
+    ("fifo", Kind::Style),
+    ("socket", Kind::Style),
+    ("permissions", Kind::Table(PERMISSION_COLOR_KEYS)),
+    ("git", Kind::Table(GIT_COLOR_KEYS)),
+    ("extensions", Kind::StyleMap),
+    ("rules", Kind::Rules),
+    ("categories", Kind::Table(CATEGORY_COLOR_KEYS)),
+    ("ls_colors", Kind::Value),
+];
+
+const PERMISSION_COLOR_KEYS: &[(&str, Kind)] = &[
+    ("file_type", Kind::Style),
+    ("read", Kind::Style),
+    ("write", Kind::Style),
+    ("execute", Kind::Style),
+    ("special", Kind::Style),
+    ("none", Kind::Style),
+    ("octal", Kind::Style),
+];
+
+const GIT_COLOR_KEYS: &[(&str, Kind)] = &[
+    ("new", Kind::Style),
+    ("modified", Kind::Style),
+    ("deleted", Kind::Style),
+    ("renamed", Kind::Style),
+    ("type_change", Kind::Style),
+    ("ignored", Kind::Style),
+    ("conflicted", Kind::Style),
+];
+
+const CATEGORY_COLOR_KEYS: &[(&str, Kind)] = &[
+    ("archive", Kind::Style),
+    ("image", Kind::Style),
+    ("audio", Kind::Style),
+    ("video", Kind::Style),
+    ("document", Kind::Style),
+    ("source", Kind::Style),
+    ("config", Kind::Style),
+    ("temporary", Kind::Style),
+];
+
+const RULE_KEYS: &[(&str, Kind)] = &[("pattern", Kind::Value), ("color", Kind::Style)];
+
+const ICON_KEYS: &[(&str, Kind)] = &[
+    ("directory", Kind::Value),
+    ("executable", Kind::Value),
+    ("regular", Kind::Value),
+    ("symlink", Kind::Value),
+    ("broken_symlink", Kind::Value),
+    ("block_device", Kind::Value),
+    ("char_device", Kind::Value),
+    ("fifo", Kind::Value),
+    ("socket", Kind::Value),
+    ("builtin", Kind::Value),
+    ("extensions", Kind::ValueMap),
+    ("filenames", Kind::ValueMap),
+    ("colors", Kind::Table(ICON_COLOR_KEYS)),
+];
+
+const ICON_COLOR_KEYS: &[(&str, Kind)] = &[
+    ("directory", Kind::Style),
+    ("executable", Kind::Style),
+    ("regular", Kind::Style),
+    ("hidden", Kind::Style),
+    ("symlink", Kind::Style),
+    ("broken_symlink", Kind::Style),
+    ("block_device", Kind::Style),
+    ("char_device", Kind::Style),
+    ("fifo", Kind::Style),
+    ("socket", Kind::Style),
+];
+
+const DISPLAY_KEYS: &[(&str, Kind)] = &[
+    ("column_spacing", Kind::Value),
+    ("max_rows", Kind::Value),
+    ("compact_max_rows", Kind::Value),
+    ("sort_field", Kind::Value),
+    ("sort_order", Kind::Value),
+    ("datetime_format", Kind::Value),
+    ("tree", Kind::Table(TREE_KEYS)),
+    ("long_format_fields", Kind::LongFormatFields),
+    ("long_format_titles", Kind::Value),
+    ("long_format_title_color", Kind::Style),
+    ("permissions_style", Kind::Value),
+    ("layout", Kind::Value),
+    ("format", Kind::Format),
+    ("color", Kind::Value),
+    ("icons", Kind::Value),
+    ("exclude", Kind::ExcludePatterns),
+    ("local_config", Kind::Value),
+    ("git_ignore", Kind::Value),
+    ("git_status", Kind::Value),
+];
+
+const TREE_KEYS: &[(&str, Kind)] = &[("style", Kind::TreeStyle), ("max_depth", Kind::Value)];
+
+/// Checks a config file's contents, returning every problem found. Syntax
+/// errors stop the check early, as nothing after them can be trusted.
+pub fn validate_config(path: &Path, contents: &str) -> Vec<ConfigIssue> {
+    let mut checker = Checker {
+        path,
+        contents,
+        issues: Vec::new(),
+    };
+
+    let table = match DeTable::parse(contents) {
+        Ok(table) => table,
+        Err(error) => {
+            checker.report(error.span().unwrap_or(0..0), error.message().to_string());
+            return checker.issues;
+        }
+    };
+    checker.check_table(table.get_ref(), ROOT_KEYS, "");
+
+    // Value types, enum variants and the like are checked by deserializing
+    if let Err(error) = toml::from_str::<Config>(contents) {
+        checker.report(error.span().unwrap_or(0..0), error.message().to_string());
+    }
+
+    checker
+        .issues
+        .sort_by_key(|issue| (issue.line, issue.column));
+    checker.issues
+}
+
+struct Checker<'a> {
+    path: &'a Path,
+    contents: &'a str,
+    issues: Vec<ConfigIssue>,
+}
+
+impl Checker<'_> {
+    fn check_table(&mut self, table: &DeTable, keys: &[(&str, Kind)], prefix: &str) {
+        for (key, value) in table.iter() {
+            let name = key.get_ref().as_ref();
+            match keys.iter().find(|(known, _)| *known == name) {
+                Some((_, kind)) => self.check_value(value, *kind, &format!("{prefix}{name}")),
+                None => {
+                    let message = format!("unknown key `{prefix}{name}`");
+                    let suggestion = closest(name, keys.iter().map(|(known, _)| *known));
+                    self.report(key.span(), with_suggestion(message, suggestion));
+                }
+            }
+        }
+    }
+
+    fn check_value(&mut self, value: &Spanned<DeValue>, kind: Kind, name: &str) {
+        match (kind, value.get_ref()) {
+            (Kind::Table(keys), DeValue::Table(table)) => {
+                self.check_table(table, keys, &format!("{name}."));
+            }
+            (Kind::Style, DeValue::String(style)) => self.check_style(style, value.span()),
+            (Kind::StyleMap, DeValue::Table(table)) => {
+                for (key, value) in table.iter() {
+                    self.check_value(value, Kind::Style, &format!("{name}.{}", key.get_ref()));
+                }
+            }
+            (Kind::Profiles, DeValue::Table(profiles)) => {
+                for (profile, value) in profiles.iter() {
+                    let name = format!("{name}.{}", profile.get_ref());
+                    self.check_value(value, Kind::Table(PROFILE_KEYS), &name);
+                }
+            }
+            (Kind::Rules, DeValue::Array(rules)) => {
+                for rule in rules {
+                    self.check_value(rule, Kind::Table(RULE_KEYS), name);
+                }
+            }
+            (Kind::LongFormatFields, DeValue::Array(fields)) => {
+                for field in fields {
+                    if let DeValue::String(field_name) = field.get_ref() {
+                        self.check_choice(
+                            field_name,
+                            LONG_FORMAT_FIELDS,
+                            "long format field",
+                            field.span(),
+                        );
+                    }
+                }
+            }
+            (Kind::TreeStyle, DeValue::String(tree_style)) => {
+                self.check_choice(tree_style, TREE_STYLES, "tree style", value.span());
+            }
+            (Kind::Format, DeValue::String(format)) => {
+                let formatters = FormatterRegistry::default();
+                self.check_choice(format, &formatters.names(), "format", value.span());
+            }
+            (Kind::ExcludePatterns, DeValue::Array(patterns)) => {
+                for pattern in patterns {
+                    if let DeValue::String(text) = pattern.get_ref() {
+                        if let Some(reason) = pattern_problem(text) {
+                            let message = format!("invalid exclude pattern `{text}`: {reason}");
+                            self.report(pattern.span(), message);
+                        }
+                    }
+                }
+            }
+            (Kind::ProfileArgs, args) => self.check_profile_args(args, name, value.span()),
+            _ => {}
+        }
+    }
+
+    fn check_profile_args(&mut self, value: &DeValue, name: &str, span: Range<usize>) {
+        let flags: Option<Vec<String>> = match value {
+            DeValue::Array(flags) => flags
+                .iter()
+                .map(|flag| match flag.get_ref() {
+                    DeValue::String(flag) => Some(flag.to_string()),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        };
+        let Some(flags) = flags else {
+            self.report(span, format!("`{name}` must be an array of strings"));
+            return;
+        };
+
+        match Args::try_parse_args_from(std::iter::once("lx".to_string()).chain(flags)) {
+            Ok(args) => {
+                if let Some(flag) = args.config_flag() {
+                    let message =
+                        format!("invalid `{name}`: `{flag}` is not allowed in profile args");
+                    self.report(span, message);
+                }
+            }
+            Err(error) => {
+                let message = error.to_string();
+                let message = message.lines().next().unwrap_or_default();
+                let message = message.strip_prefix("error: ").unwrap_or(message);
+                self.report(span, format!("invalid `{name}`: {message}"));
+            }
+        }
+    }
+
+    fn check_style(&mut self, style: &str, span: Range<usize>) {
+        let Err(error) = Style::parse(style) else {
+            return;
+        };
+
+        let keywords = style::keywords();
+        let suggestion = style::unknown_words(style)
+            .first()
+            .and_then(|word| closest(word, keywords.iter().map(String::as_str)));
+        self.push_issue(span, with_suggestion(error, suggestion), true);
+    }
+
+    fn check_choice(&mut self, value: &str, choices: &[&str], what: &str, span: Range<usize>) {
+        if choices.contains(&value) {
+            return;
+        }
+
+        let message = format!("unknown {what} `{value}`");
+        self.report(
+            span,
+            with_suggestion(message, closest(value, choices.iter().copied())),
+        );
+    }
+
+    fn report(&mut self, span: Range<usize>, message: String) {
+        self.push_issue(span, message, false);
+    }
+
+    fn push_issue(&mut self, span: Range<usize>, message: String, is_error: bool) {
+        let before = &self.contents[..span.start.min(self.contents.len())];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+        self.issues.push(ConfigIssue {
+            path: self.path.to_path_buf(),
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message,
+            is_error,
+        });
+    }
+}
+
+fn with_suggestion(message: String, suggestion: Option<&str>) -> String {
+    match suggestion {
+        Some(suggestion) => format!("{message} (did you mean `{suggestion}`?)"),
+        None => message,
+    }
+}
+
+/// The candidate closest to `value` by edit distance, if it is close enough
+/// to plausibly be a typo.
+fn closest<'a>(value: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (value.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(value, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings, counted in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
@@ -1,7 +1,7 @@
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 fn create_temp_dir(label: &str) -> PathBuf {
@@ -14,9 +14,14 @@ fn create_temp_dir(label: &str) -> PathBuf {
     path
 }
 
-fn lx_command(home_dir: &Path) -> Command {
+/// Runs lx with the given config file, or with built-in defaults for `None`.
+fn lx_command(config_path: Option<&Path>) -> Command {
     let mut command = Command::new(env!("CARGO_BIN_EXE_lx"));
-    command.env("HOME", home_dir);
+    command.env_remove("LS_COLORS").env_remove("LSCOLORS");
+    match config_path {
+        Some(path) => command.arg("--config").arg(path),
+        None => command.arg("--no-config"),
+    };
     command
 }
 
@@ -25,21 +30,33 @@ fn recursive_listing_reports_unreadable_directories() {
     let temp_dir = create_temp_dir("recursive-permissions");
     let blocked_dir = temp_dir.join("blocked");
     fs::create_dir(&blocked_dir).expect("create blocked directory");
+    fs::create_dir(temp_dir.join("open")).expect("create open directory");
+    fs::write(temp_dir.join("open/inside.txt"), "").expect("create file");
     fs::set_permissions(&blocked_dir, fs::Permissions::from_mode(0o000))
         .expect("make directory unreadable");
+    // Permissions do not apply to root, so there is nothing to test
+    let is_unreadable = fs::read_dir(&blocked_dir).is_err();
 
-    let output = lx_command(&temp_dir)
-        .args(["-r", temp_dir.to_str().expect("UTF-8 temp path")])
+    let output = lx_command(None)
+        .args([
+            "-r",
+            "--icons=never",
+            temp_dir.to_str().expect("UTF-8 temp path"),
+        ])
         .output()
         .expect("run lx");
 
     fs::set_permissions(&blocked_dir, fs::Permissions::from_mode(0o700))
         .expect("restore directory permissions");
     fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+    if !is_unreadable {
+        return;
+    }
 
-    assert!(
-        !output.status.success(),
-        "recursive lx should return a non-zero exit status for unreadable directories"
+    assert_eq!(
+        output.status.code(),
+        Some(1),
+        "an unreadable subdirectory is a minor problem"
     );
 
     let stderr = String::from_utf8_lossy(&output.stderr);
@@ -47,4 +64,1193 @@ fn recursive_listing_reports_unreadable_directories() {
         stderr.contains("Permission denied"),
         "stderr should report the directory error: {stderr}"
     );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("├─ blocked/ [Permission denied]\n"),
+        "the tree should mark the directory: {stdout}"
+    );
+    assert!(
+        stdout.contains("╰─ inside.txt\n"),
+        "the rest of the tree should still be listed: {stdout}"
+    );
+}
+
+#[test]
+fn long_listing_shows_symlink_targets() {
+    let temp_dir = create_temp_dir("symlinks");
+    fs::write(temp_dir.join("target.txt"), "").expect("create symlink target");
+    std::os::unix::fs::symlink("target.txt", temp_dir.join("link")).expect("create symlink");
+    std::os::unix::fs::symlink("missing", temp_dir.join("dangling"))
+        .expect("create broken symlink");
+
+    let output = lx_command(None)
+        .args(["-l", temp_dir.to_str().expect("UTF-8 temp path")])
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for expected in ["link -> target.txt", "dangling -> missing"] {
+        let line = stdout
+            .lines()
+            .find(|line| line.contains(expected))
+            .unwrap_or_else(|| panic!("missing `{expected}` in output: {stdout}"));
+        assert!(
+            line.starts_with('l'),
+            "symlinks should use the `l` type: {line}"
+        );
+    }
+}
+
+#[test]
+fn long_listing_shows_special_file_types() {
+    let temp_dir = create_temp_dir("special-files");
+    let status = Command::new("mkfifo")
+        .arg(temp_dir.join("pipe"))
+        .status()
+        .expect("run mkfifo");
+    assert!(status.success(), "mkfifo should create a named pipe");
+
+    let output = lx_command(None)
+        .args(["-l", temp_dir.to_str().expect("UTF-8 temp path")])
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout
+        .lines()
+        .find(|line| line.ends_with("pipe"))
+        .unwrap_or_else(|| panic!("missing pipe in output: {stdout}"));
+    assert!(
+        line.starts_with('p'),
+        "named pipes should use the `p` type: {line}"
+    );
+}
+
+#[test]
+fn long_listing_shows_special_permission_bits() {
+    let temp_dir = create_temp_dir("special-bits");
+    let config_path = temp_dir.join("config.toml");
+    fs::write(&config_path, "[display]\npermissions_style = \"both\"\n").expect("write config");
+    let setuid = temp_dir.join("setuid");
+    fs::write(&setuid, "").expect("create setuid file");
+    fs::set_permissions(&setuid, fs::Permissions::from_mode(0o4755)).expect("set setuid bit");
+    let sticky = temp_dir.join("sticky");
+    fs::create_dir(&sticky).expect("create sticky directory");
+    fs::set_permissions(&sticky, fs::Permissions::from_mode(0o1776)).expect("set sticky bit");
+
+    let output = lx_command(Some(&config_path))
+        .args(["-l", temp_dir.to_str().expect("UTF-8 temp path")])
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("-rwsr-xr-x 4755"),
+        "setuid should render as `s`: {stdout}"
+    );
+    assert!(
+        stdout.contains("drwxrwxrwT 1776"),
+        "sticky without execute should render as `T`: {stdout}"
+    );
+}
+
+#[test]
+fn grid_layout_fits_entries_to_terminal_width() {
+    let temp_dir = create_temp_dir("grid");
+    for index in 1..=12 {
+        fs::write(temp_dir.join(format!("f{index:02}")), "").expect("create file");
+    }
+
+    let output = lx_command(None)
+        .env("COLUMNS", "40")
+        .args(["-C", temp_dir.to_str().expect("UTF-8 temp path")])
+        .output()
+        .expect("run lx");
+    let empty_dir = temp_dir.join("empty");
+    fs::create_dir(&empty_dir).expect("create empty dir");
+    let empty = lx_command(None)
+        .args(["-C", "-s", "size"])
+        .arg(&empty_dir)
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert!(empty.status.success(), "{empty:?}");
+    assert!(empty.stdout.is_empty(), "{empty:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Each entry is an icon, a space and three characters wide, so six columns fit
+    assert_eq!(stdout.lines().count(), 2, "unexpected grid: {stdout}");
+    assert!(stdout.lines().all(|line| line.chars().count() <= 40));
+}
+
+#[test]
+fn color_policy_follows_flags_and_environment() {
+    let temp_dir = create_temp_dir("color");
+    fs::create_dir(temp_dir.join("dir")).expect("create directory");
+    let target = temp_dir.to_str().expect("UTF-8 temp path");
+
+    let run = |args: &[&str], env: &[(&str, &str)]| {
+        let output = lx_command(None)
+            .env_remove("NO_COLOR")
+            .env_remove("CLICOLOR")
+            .env_remove("CLICOLOR_FORCE")
+            .envs(env.iter().copied())
+            .args(args)
+            .arg(target)
+            .output()
+            .expect("run lx");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let piped = run(&[], &[]);
+    let forced = run(&["--color=always"], &[("NO_COLOR", "1")]);
+    let clicolor_forced = run(&[], &[("CLICOLOR_FORCE", "1")]);
+    let no_icons = run(&["--icons=never"], &[]);
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert!(
+        !piped.contains('\u{1b}'),
+        "piped output should be plain: {piped:?}"
+    );
+    assert!(
+        forced.contains('\u{1b}'),
+        "--color=always should win: {forced:?}"
+    );
+    assert!(clicolor_forced.contains('\u{1b}'));
+    assert_eq!(no_icons.trim_end(), "dir");
+}
+
+#[test]
+fn json_output_encodes_entries_and_trees() {
+    let temp_dir = create_temp_dir("json");
+    fs::create_dir(temp_dir.join("nested")).expect("create directory");
+    fs::write(temp_dir.join("nested/data.txt"), "12345").expect("create file");
+    let target = temp_dir.to_str().expect("UTF-8 temp path");
+
+    let flat = lx_command(None)
+        .args(["--output", "ndjson", target])
+        .output()
+        .expect("run lx");
+    let tree = lx_command(None)
+        .args(["-r", "--output=json", target])
+        .output()
+        .expect("run lx");
+    let file_path = temp_dir.join("nested/data.txt");
+    let file_target = file_path.to_str().expect("UTF-8 temp path");
+    let targets = lx_command(None)
+        .args(["--output", "json", file_target, target])
+        .output()
+        .expect("run lx");
+    let target_lines = lx_command(None)
+        .args(["--output", "ndjson", file_target, target])
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    let flat: Vec<serde_json::Value> = String::from_utf8_lossy(&flat.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("valid NDJSON line"))
+        .collect();
+    assert_eq!(flat.len(), 1);
+    assert_eq!(flat[0]["name"], "nested");
+    assert_eq!(flat[0]["type"], "directory");
+
+    let tree: serde_json::Value = serde_json::from_slice(&tree.stdout).expect("valid JSON tree");
+    let file = &tree["children"][0]["children"][0];
+    assert_eq!(file["name"], "data.txt");
+    assert_eq!(file["size"], 5);
+    assert!(file["mode"].is_u64() && file["uid"].is_u64());
+    assert!(
+        file["modified"]
+            .as_str()
+            .is_some_and(|modified| chrono::DateTime::parse_from_rfc3339(modified).is_ok())
+    );
+
+    // Several targets make one document keyed by target
+    let targets: serde_json::Value =
+        serde_json::from_slice(&targets.stdout).expect("valid JSON document");
+    assert_eq!(targets[file_target][0]["size"], 5);
+    assert_eq!(targets[target][0]["name"], "nested");
+    assert_eq!(targets.as_object().map(|targets| targets.len()), Some(2));
+
+    let target_lines: Vec<serde_json::Value> = String::from_utf8_lossy(&target_lines.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("valid NDJSON line"))
+        .collect();
+    assert_eq!(target_lines.len(), 2);
+    assert_eq!(target_lines[0]["target"], file_target);
+    assert_eq!(target_lines[1]["target"], target);
+    assert_eq!(target_lines[1]["name"], "nested");
+}
+
+#[test]
+fn lists_multiple_targets_and_reports_missing_ones() {
+    let temp_dir = create_temp_dir("multiple-targets");
+    for directory in ["first", "second"] {
+        fs::create_dir(temp_dir.join(directory)).expect("create directory");
+        fs::write(
+            temp_dir.join(directory).join(format!("{directory}.txt")),
+            "",
+        )
+        .expect("create file");
+    }
+    fs::write(temp_dir.join("loose.txt"), "").expect("create file");
+
+    let output = lx_command(None)
+        .current_dir(&temp_dir)
+        .args([
+            "-1",
+            "--icons=never",
+            "second",
+            "missing",
+            "loose.txt",
+            "first",
+        ])
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert_eq!(
+        output.status.code(),
+        Some(2),
+        "a missing target should make lx fail"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("cannot access 'missing'"),
+        "stderr: {stderr}"
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            "loose.txt",
+            "",
+            "second:",
+            "second.txt",
+            "",
+            "first:",
+            "first.txt"
+        ]
+    );
+}
+
+#[test]
+fn icon_tables_match_names_and_extensions_case_insensitively() {
+    let temp_dir = create_temp_dir("icon-tables");
+    let config_path = temp_dir.join("config.toml");
+    fs::write(
+        &config_path,
+        "[icons.extensions]\n\"TAR.GZ\" = \"T\"\nrs = \"R\"\n\n[icons.filenames]\n\"build.RS\" = \"B\"\n",
+    )
+    .expect("write config");
+    let listing = temp_dir.join("listing");
+    fs::create_dir(&listing).expect("create listing dir");
+    for name in ["archive.Tar.Gz", "main.rs", "Build.rs"] {
+        fs::write(listing.join(name), "").expect("create file");
+    }
+
+    let output = lx_command(Some(&config_path))
+        .args(["-1", listing.to_str().expect("UTF-8 temp path")])
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for expected in ["T archive.Tar.Gz", "R main.rs", "B Build.rs"] {
+        assert!(
+            stdout.lines().any(|line| line == expected),
+            "missing `{expected}`: {stdout}"
+        );
+    }
+}
+
+#[test]
+fn colour_rules_extensions_and_categories_apply_in_order() {
+    let temp_dir = create_temp_dir("colour-rules");
+    let config_path = temp_dir.join("config.toml");
+    fs::write(
+        &config_path,
+        "[colors.extensions]\nMD = \"blue\"\n\n[[colors.rules]]\npattern = \"special.*\"\ncolor = \"cyan\"\n",
+    )
+    .expect("write config");
+    let listing = temp_dir.join("listing");
+    fs::create_dir(&listing).expect("create listing dir");
+    for name in ["notes.md", "special.md", "photo.PNG", "plain"] {
+        fs::write(listing.join(name), "").expect("create file");
+    }
+
+    let output = lx_command(Some(&config_path))
+        .args([
+            "-1",
+            "--color=always",
+            "--icons=never",
+            listing.to_str().expect("UTF-8 temp path"),
+        ])
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for expected in [
+        "\u{1b}[34mnotes.md",
+        "\u{1b}[36mspecial.md",
+        "\u{1b}[35mphoto.PNG",
+        "\u{1b}[37mplain",
+    ] {
+        assert!(
+            stdout.contains(expected),
+            "missing {expected:?}: {stdout:?}"
+        );
+    }
+}
+
+#[test]
+fn ls_colors_apply_according_to_config_precedence() {
+    let temp_dir = create_temp_dir("ls-colors");
+    let config_path = temp_dir.join("config.toml");
+    let listing = temp_dir.join("listing");
+    fs::create_dir_all(listing.join("docs")).expect("create listing dirs");
+    for name in ["notes.md", "data.xyz"] {
+        fs::write(listing.join(name), "").expect("create file");
+    }
+
+    let run = |config: &str, variable: &str, value: &str| {
+        fs::write(&config_path, config).expect("write config");
+        let output = lx_command(Some(&config_path))
+            .env(variable, value)
+            .args([
+                "-1",
+                "--color=always",
+                "--icons=never",
+                listing.to_str().expect("UTF-8 temp path"),
+            ])
+            .output()
+            .expect("run lx");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let ls_colors = "di=01;35:*.md=04;33:*.xyz=38;5;208";
+
+    let fallback = run("", "LS_COLORS", ls_colors);
+    let prefer = run("[colors]\nls_colors = \"prefer\"\n", "LS_COLORS", ls_colors);
+    let ignore = run("[colors]\nls_colors = \"ignore\"\n", "LS_COLORS", ls_colors);
+    let bsd = run(
+        "[colors]\nls_colors = \"prefer\"\n",
+        "LSCOLORS",
+        "Gxfxcxdxbxegedabagacad",
+    );
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    for (stdout, expected) in [
+        (&fallback, "\u{1b}[1;34mdocs"),
+        (&fallback, "\u{1b}[38;5;208mdata.xyz"),
+        (&prefer, "\u{1b}[01;35mdocs"),
+        (&prefer, "\u{1b}[04;33mnotes.md"),
+        (&ignore, "\u{1b}[37mdata.xyz"),
+        (&bsd, "\u{1b}[1;36mdocs"),
+    ] {
+        assert!(
+            stdout.contains(expected),
+            "missing {expected:?}: {stdout:?}"
+        );
+    }
+}
+
+#[test]
+fn styles_accept_truecolor_256_colours_and_attributes() {
+    let temp_dir = create_temp_dir("styles");
+    let config_path = temp_dir.join("config.toml");
+    fs::write(
+        &config_path,
+        "[colors]\ndirectory = \"bold #ff8800 on #202020\"\nregular = \"ansi(208)\"\nsymlink = \"italic underline rgb(1, 2, 3)\"\n",
+    )
+    .expect("write config");
+    let listing = temp_dir.join("listing");
+    fs::create_dir_all(listing.join("docs")).expect("create listing dirs");
+    fs::write(listing.join("plain"), "").expect("create file");
+    std::os::unix::fs::symlink("plain", listing.join("link")).expect("create symlink");
+
+    let output = lx_command(Some(&config_path))
+        .args([
+            "-1",
+            "--color=always",
+            "--icons=never",
+            listing.to_str().expect("UTF-8 temp path"),
+        ])
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for expected in [
+        "\u{1b}[1;38;2;255;136;0;48;2;32;32;32mdocs",
+        "\u{1b}[38;5;208mplain",
+        "\u{1b}[3;4;38;2;1;2;3mlink",
+    ] {
+        assert!(
+            stdout.contains(expected),
+            "missing {expected:?}: {stdout:?}"
+        );
+    }
+}
+
+#[test]
+fn check_config_reports_locations_and_suggestions() {
+    let temp_dir = create_temp_dir("check-config");
+    let config_path = temp_dir.join("config.toml");
+
+    fs::write(
+        &config_path,
+        "[colors]\ndirectory = \"bold blu\"\n\n[display]\nlong_format_fields = [\"size\", \"ownr\"]\n\n[display.tree]\nstyle = \"indnet\"\n",
+    )
+    .expect("write config");
+    let invalid = lx_command(Some(&config_path))
+        .arg("--check-config")
+        .output()
+        .expect("run lx");
+    let listing = lx_command(Some(&config_path))
+        .arg(&temp_dir)
+        .output()
+        .expect("run lx");
+
+    fs::write(&config_path, "[colors]\ndirectory = \"bold #ff8800\"\n").expect("write config");
+    let valid = lx_command(Some(&config_path))
+        .arg("--check-config")
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert_eq!(invalid.status.code(), Some(5));
+    let stderr = String::from_utf8_lossy(&invalid.stderr);
+    let path = config_path.display();
+    for expected in [
+        format!("{path}:2:13: unknown colour `blu` (did you mean `blue`?)"),
+        format!("{path}:5:31: unknown long format field `ownr` (did you mean `owner`?)"),
+        format!("{path}:8:9: unknown tree style `indnet` (did you mean `indent`?)"),
+    ] {
+        assert!(
+            stderr.contains(&expected),
+            "missing {expected:?}: {stderr:?}"
+        );
+    }
+
+    // A style lx cannot show stops a listing too, rather than turning white
+    assert_eq!(listing.status.code(), Some(5));
+    assert!(listing.stdout.is_empty(), "{listing:?}");
+    let stderr = String::from_utf8_lossy(&listing.stderr);
+    assert!(
+        stderr.contains(&format!("lx: {path}:2:13: unknown colour `blu`")),
+        "{stderr:?}"
+    );
+
+    assert!(valid.status.success(), "{valid:?}");
+}
+
+#[test]
+fn config_path_follows_flag_environment_and_xdg_order() {
+    let temp_dir = create_temp_dir("config-path");
+    let home = temp_dir.join("home");
+    let xdg = temp_dir.join("xdg");
+    fs::create_dir_all(home.join(".config/lx")).expect("create home config dir");
+    fs::create_dir_all(xdg.join("lx")).expect("create xdg config dir");
+    let home_config = home.join(".config/lx/config");
+    let xdg_config = xdg.join("lx/config.toml");
+    let env_config = temp_dir.join("env.toml");
+    let flag_config = temp_dir.join("flag.toml");
+    for path in [&home_config, &xdg_config, &env_config, &flag_config] {
+        fs::write(path, "").expect("write config");
+    }
+
+    let loaded = |envs: &[(&str, &Path)], args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_lx"))
+            .env("HOME", &home)
+            .env_remove("XDG_CONFIG_HOME")
+            .env_remove("LX_CONFIG")
+            .envs(envs.iter().copied())
+            .args(args)
+            .arg("--check-config")
+            .output()
+            .expect("run lx");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let home_only = loaded(&[], &[]);
+    let with_xdg = loaded(&[("XDG_CONFIG_HOME", &xdg)], &[]);
+    let with_env = loaded(
+        &[("XDG_CONFIG_HOME", &xdg), ("LX_CONFIG", &env_config)],
+        &[],
+    );
+    let with_flag = loaded(
+        &[("LX_CONFIG", &env_config)],
+        &["--config", flag_config.to_str().expect("UTF-8 temp path")],
+    );
+    let missing = Command::new(env!("CARGO_BIN_EXE_lx"))
+        .args(["--config", "/nonexistent/lx.toml", "-1"])
+        .arg(&temp_dir)
+        .output()
+        .expect("run lx");
+    let empty_home = temp_dir.join("empty-home");
+    let unconfigured = Command::new(env!("CARGO_BIN_EXE_lx"))
+        .env("HOME", &empty_home)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("LX_CONFIG")
+        .arg("--config-path")
+        .arg(&temp_dir)
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert!(unconfigured.status.success(), "{unconfigured:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&unconfigured.stdout),
+        format!(
+            "{} (not found)\n",
+            empty_home.join(".config/lx/config").display()
+        )
+    );
+    assert_eq!(home_only, format!("{}: ok\n", home_config.display()));
+    assert_eq!(with_xdg, format!("{}: ok\n", xdg_config.display()));
+    assert_eq!(with_env, format!("{}: ok\n", env_config.display()));
+    assert_eq!(with_flag, format!("{}: ok\n", flag_config.display()));
+    assert_eq!(missing.status.code(), Some(5));
+    assert!(
+        String::from_utf8_lossy(&missing.stderr)
+            .contains("cannot access config file '/nonexistent/lx.toml'"),
+        "{missing:?}"
+    );
+}
+
+#[test]
+fn local_config_files_merge_over_the_global_config() {
+    let temp_dir = create_temp_dir("local-config");
+    let config_path = temp_dir.join("config.toml");
+    let repo = temp_dir.join("repo");
+    let app = repo.join("app");
+    for directory in ["node_modules", "target", "src"] {
+        fs::create_dir_all(app.join(directory)).expect("create listing dir");
+    }
+    for name in ["a.txt", "b.txt"] {
+        fs::write(app.join(name), "").expect("create file");
+    }
+    fs::write(
+        &config_path,
+        "[display]\nlong_format_fields = [\"filename\"]\nsort_field = \"name\"\n",
+    )
+    .expect("write config");
+    fs::write(
+        repo.join(".lx.toml"),
+        "[display]\nexclude = [\"node_modules\", \"target\"]\nsort_order = \"desc\"\n",
+    )
+    .expect("write repo config");
+    fs::write(app.join(".lx.toml"), "[display.tree]\nstyle = \"indent\"\n")
+        .expect("write app config");
+    // A second target outside the repository gets its own config
+    let other = temp_dir.join("other");
+    fs::create_dir_all(other.join("node_modules")).expect("create other dir");
+    fs::write(other.join("a.txt"), "").expect("create file");
+    fs::write(other.join(".lx.toml"), "[display]\nexclude = [\"a.txt\"]\n")
+        .expect("write other config");
+
+    let listing = lx_command(Some(&config_path))
+        .args(["-l", "--icons=never"])
+        .arg(&app)
+        .output()
+        .expect("run lx");
+    let files = lx_command(Some(&config_path))
+        .arg("--check-config")
+        .arg(&app)
+        .output()
+        .expect("run lx");
+    let both = lx_command(Some(&config_path))
+        .args(["-l", "--icons=never"])
+        .arg(&app)
+        .arg(&other)
+        .output()
+        .expect("run lx");
+    let both_paths = lx_command(Some(&config_path))
+        .arg("--config-path")
+        .arg(&app)
+        .arg(&other)
+        .output()
+        .expect("run lx");
+    let other_config = other
+        .canonicalize()
+        .expect("canonical other")
+        .join(".lx.toml");
+    let repo_config = repo
+        .canonicalize()
+        .expect("canonical repo")
+        .join(".lx.toml");
+    let app_config = app.canonicalize().expect("canonical app").join(".lx.toml");
+    fs::write(&config_path, "[display]\nlocal_config = false\n").expect("write config");
+    let disabled = lx_command(Some(&config_path))
+        .args(["-1", "--icons=never"])
+        .arg(&app)
+        .output()
+        .expect("run lx");
+    fs::write(&config_path, "").expect("write config");
+    fs::write(app.join(".lx.toml"), "[display]\nsort_order = 3\n").expect("write app config");
+    let mistyped = lx_command(Some(&config_path))
+        .arg(&app)
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert_eq!(mistyped.status.code(), Some(5));
+    assert!(
+        String::from_utf8_lossy(&mistyped.stderr)
+            .contains(&format!("invalid config file '{}'", app_config.display())),
+        "{mistyped:?}"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&listing.stdout),
+        "src\nb.txt\na.txt\n"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&files.stdout),
+        format!(
+            "{}: ok\n{}: ok\n{}: ok\n",
+            config_path.display(),
+            repo_config.display(),
+            app_config.display()
+        )
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&both.stdout),
+        format!(
+            "{}:\nsrc\nb.txt\na.txt\n\n{}:\nnode_modules\n",
+            app.display(),
+            other.display()
+        )
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&both_paths.stdout),
+        format!(
+            "{}\n{}\n{}\n{}\n",
+            config_path.display(),
+            repo_config.display(),
+            app_config.display(),
+            other_config.display()
+        )
+    );
+    assert!(String::from_utf8_lossy(&disabled.stdout).contains("node_modules"));
+}
+
+#[test]
+fn profiles_override_config_and_default_flags() {
+    let temp_dir = create_temp_dir("profiles");
+    let config_path = temp_dir.join("config.toml");
+    let listing = temp_dir.join("listing");
+    fs::create_dir(&listing).expect("create listing dir");
+    let script = listing.join("run.sh");
+    fs::write(&script, "abc").expect("create file");
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o750)).expect("chmod file");
+    fs::write(
+        &config_path,
+        "[display]\nlong_format_fields = [\"filename\"]\n\n\
+         [profiles.audit]\nargs = [\"-l\"]\n\n\
+         [profiles.audit.display]\nlong_format_fields = [\"permissions\", \"filename\"]\npermissions_style = \"octal\"\n\n\
+         [profiles.dev.display]\nlong_format_fields = [\"size\", \"filename\"]\n\n\
+         [profiles.flag]\nargs = \"-l\"\n\n\
+         [profiles.elsewhere]\nargs = [\"--no-config\"]\n",
+    )
+    .expect("write config");
+
+    let run = |profile_env: Option<&str>, args: &[&str]| {
+        let mut command = lx_command(Some(&config_path));
+        command.env_remove("LX_PROFILE");
+        if let Some(profile) = profile_env {
+            command.env("LX_PROFILE", profile);
+        }
+        command
+            .args(["--icons=never"])
+            .args(args)
+            .arg(&listing)
+            .output()
+            .expect("run lx")
+    };
+    let audit = run(None, &["--profile", "audit"]);
+    let audit_with_long = run(None, &["--profile=audit", "-l"]);
+    let dev = run(Some("dev"), &["-l"]);
+    let unknown = run(None, &["--profile", "nope"]);
+    // The command line's -1 replaces the profile's conflicting -l
+    let audit_one_per_line = run(None, &["--profile", "audit", "-1"]);
+    let flag = run(None, &["--profile", "flag"]);
+    let elsewhere = run(None, &["--profile", "elsewhere"]);
+    let check = lx_command(Some(&config_path))
+        .arg("--check-config")
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert_eq!(String::from_utf8_lossy(&audit.stdout), "0750  run.sh\n");
+    assert_eq!(
+        String::from_utf8_lossy(&audit_with_long.stdout),
+        "0750  run.sh\n"
+    );
+    assert_eq!(String::from_utf8_lossy(&dev.stdout), "3B  run.sh\n");
+    assert_eq!(unknown.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&unknown.stderr).contains("unknown profile 'nope'"));
+    assert!(
+        audit_one_per_line.status.success(),
+        "{audit_one_per_line:?}"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&audit_one_per_line.stdout),
+        "run.sh\n"
+    );
+    assert_eq!(flag.status.code(), Some(5));
+    // Config flags act before a profile applies, so profiles cannot give them
+    assert_eq!(elsewhere.status.code(), Some(5));
+    assert!(
+        String::from_utf8_lossy(&elsewhere.stderr)
+            .contains("`--no-config` is not allowed in profile args"),
+        "{elsewhere:?}"
+    );
+    assert_eq!(check.status.code(), Some(5));
+    let check = String::from_utf8_lossy(&check.stderr);
+    assert!(
+        check.contains(":15:8: `profiles.flag.args` must be an array of strings"),
+        "{check}"
+    );
+    assert!(
+        check.contains(
+            ":18:8: invalid `profiles.elsewhere.args`: `--no-config` is not allowed in profile args"
+        ),
+        "{check}"
+    );
+}
+
+#[test]
+fn config_flags_initialise_show_and_locate_the_config() {
+    let temp_dir = create_temp_dir("config-flags");
+    let config_path = temp_dir.join("lx/config.toml");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_lx"))
+            .arg("--config")
+            .arg(&config_path)
+            .args(args)
+            .arg(&temp_dir)
+            .output()
+            .expect("run lx")
+    };
+
+    let init = run(&["--init-config"]);
+    let init_again = run(&["--init-config"]);
+    let check = run(&["--check-config"]);
+    fs::write(&config_path, "[display]\nsort_order = \"desc\"\n").expect("write config");
+    let forced = run(&["--init-config", "--force"]);
+    let written = fs::read_to_string(&config_path).expect("read config");
+    fs::write(&config_path, "[colors]\ndirectory = \"green\"\n").expect("write config");
+    let located = run(&["--config-path"]);
+    let shown = run(&["--show-config"]);
+    fs::write(&config_path, &shown.stdout).expect("write shown config");
+    let round_trip = run(&["--check-config"]);
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert!(init.status.success(), "{init:?}");
+    assert_eq!(init_again.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&init_again.stderr).contains("--force"));
+    assert!(check.status.success(), "{check:?}");
+    assert!(forced.status.success(), "{forced:?}");
+    assert!(written.starts_with("# Example configuration file for lx"));
+    assert_eq!(
+        String::from_utf8_lossy(&located.stdout),
+        format!("{}\n", config_path.display())
+    );
+    assert!(String::from_utf8_lossy(&shown.stdout).contains("directory = \"green\""));
+    assert!(round_trip.status.success(), "{round_trip:?}");
+}
+
+#[test]
+fn git_ignore_hides_entries_matched_by_ignore_files() {
+    let temp_dir = create_temp_dir("git-ignore");
+    let home = temp_dir.join("home");
+    let repo = temp_dir.join("repo");
+    for dir in [
+        home.join(".config/git"),
+        repo.join(".git/info"),
+        repo.join("target"),
+        repo.join("sub/deep"),
+    ] {
+        fs::create_dir_all(dir).expect("create dir");
+    }
+    fs::write(home.join(".config/git/ignore"), "*.bak\n").expect("write global excludes");
+    fs::write(
+        repo.join(".gitignore"),
+        "target/\n*.log\n!keep.log\n/root_only.txt\n",
+    )
+    .expect("write gitignore");
+    fs::write(repo.join(".git/info/exclude"), "secret.txt\n").expect("write exclude");
+    fs::write(repo.join(".ignore"), "*.tmp\n").expect("write ignore");
+    fs::write(repo.join("sub/.gitignore"), "local.md\n").expect("write nested gitignore");
+    for file in [
+        "a.log",
+        "keep.log",
+        "main.rs",
+        "old.bak",
+        "root_only.txt",
+        "scratch.tmp",
+        "secret.txt",
+        "sub/b.log",
+        "sub/local.md",
+        "sub/root_only.txt",
+        "sub/deep/local.md",
+        "target/out",
+    ] {
+        fs::write(repo.join(file), "").expect("create file");
+    }
+
+    let run = |args: &[&str], target: &Path| {
+        let output = lx_command(None)
+            .env("HOME", &home)
+            .env_remove("XDG_CONFIG_HOME")
+            .args(["--icons=never"])
+            .args(args)
+            .arg(target)
+            .output()
+            .expect("run lx");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let flat = run(&["--git-ignore", "-1"], &repo);
+    let nested = run(&["--git-ignore", "-1"], &repo.join("sub"));
+    let tree = run(&["--git-ignore", "-r"], &repo);
+    let overridden = run(&["--git-ignore", "--no-git-ignore", "-1"], &repo);
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert_eq!(flat, "sub\nkeep.log\nmain.rs\n");
+    assert_eq!(nested, "deep\nroot_only.txt\n");
+    assert!(tree.contains("sub/"), "tree output: {tree}");
+    assert!(tree.contains("root_only.txt"), "tree output: {tree}");
+    for hidden in [
+        "target",
+        "a.log",
+        "b.log",
+        "local.md",
+        "old.bak",
+        "scratch.tmp",
+    ] {
+        assert!(!tree.contains(hidden), "{hidden} in tree output: {tree}");
+    }
+    assert!(overridden.contains("a.log"));
+}
+
+#[test]
+fn git_status_marks_entries_and_rolls_up_directories() {
+    let temp_dir = create_temp_dir("git-status");
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(&temp_dir)
+            .args(["-c", "user.name=lx", "-c", "user.email=lx@example.com"])
+            .args(args)
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .output()
+            .is_ok_and(|output| output.status.success())
+    };
+    if !git(&["init", "-q"]) {
+        // git is not installed
+        fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+        return;
+    }
+    fs::create_dir_all(temp_dir.join("src")).expect("create src");
+    fs::create_dir_all(temp_dir.join("target")).expect("create target");
+    fs::write(temp_dir.join(".gitignore"), "target/\n").expect("write gitignore");
+    fs::write(temp_dir.join("README"), "a").expect("write README");
+    fs::write(temp_dir.join("src/lib.rs"), "a").expect("write lib.rs");
+    fs::write(temp_dir.join("src/main.rs"), "a").expect("write main.rs");
+    assert!(git(&["add", "-A"]));
+    assert!(git(&["commit", "-q", "-m", "initial"]));
+    fs::write(temp_dir.join("README"), "b").expect("modify README");
+    assert!(git(&["add", "README"]));
+    fs::write(temp_dir.join("src/main.rs"), "b").expect("modify main.rs");
+    fs::write(temp_dir.join("src/new.rs"), "").expect("write new.rs");
+    fs::write(temp_dir.join("target/out"), "").expect("write out");
+
+    let run = |args: &[&str]| {
+        let output = lx_command(None)
+            .args(["--icons=never", "--git"])
+            .args(args)
+            .arg(&temp_dir)
+            .output()
+            .expect("run lx");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let short = run(&["-1"]);
+    let tree = run(&["-r"]);
+    let long = run(&["-l"]);
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert_eq!(short, "M src\nI target\nM README\n");
+    assert!(tree.contains("├─   lib.rs\n"), "tree output: {tree}");
+    assert!(tree.contains("├─ M main.rs\n"), "tree output: {tree}");
+    assert!(tree.contains("╰─ N new.rs\n"), "tree output: {tree}");
+    assert!(tree.contains("╰─ I out\n"), "tree output: {tree}");
+    assert!(long.contains("-M  src\n"), "long output: {long}");
+    assert!(long.contains("M-  README\n"), "long output: {long}");
+}
+
+#[test]
+fn level_limits_tree_depth_and_counts_truncated_entries() {
+    let temp_dir = create_temp_dir("level");
+    fs::create_dir_all(temp_dir.join("src/nested/deep")).expect("create dirs");
+    fs::create_dir(temp_dir.join("empty")).expect("create empty dir");
+    fs::write(temp_dir.join("src/lib.rs"), "").expect("create lib.rs");
+    fs::write(temp_dir.join("src/nested/mod.rs"), "").expect("create mod.rs");
+    let config_path = temp_dir.join("config.toml");
+    fs::write(&config_path, "[display.tree]\nmax_depth = 2\n").expect("write config");
+
+    let run = |config: Option<&Path>, args: &[&str]| {
+        let output = lx_command(config)
+            .args(["--icons=never"])
+            .args(args)
+            .arg(temp_dir.join("src"))
+            .output()
+            .expect("run lx");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let level_one = run(None, &["-L", "1"]);
+    let configured = run(Some(&config_path), &["-r"]);
+    let overridden = run(Some(&config_path), &["-L", "1", "--output", "ndjson"]);
+    let empty = lx_command(None)
+        .args(["--icons=never", "-L", "1"])
+        .arg(&temp_dir)
+        .output()
+        .expect("run lx");
+    let long = run(None, &["-l", "-L", "1"]);
+    // Like -L 0, a depth of 0 is rejected rather than cutting off every directory
+    fs::write(&config_path, "[display.tree]\nmax_depth = 0\n").expect("write config");
+    let zero = lx_command(Some(&config_path))
+        .arg("--check-config")
+        .output()
+        .expect("run lx");
+    let zero_listing = lx_command(Some(&config_path))
+        .arg("-r")
+        .arg(temp_dir.join("src"))
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert!(
+        level_one.ends_with("├─ lib.rs\n╰─ nested/ … 2 entries\n"),
+        "level 1 output: {level_one}"
+    );
+    assert!(
+        configured.ends_with("╰─ nested/\n   ├─ deep/\n   ╰─ mod.rs\n"),
+        "configured output: {configured}"
+    );
+    assert!(overridden.contains("\"truncated_entries\":2"));
+    assert!(String::from_utf8_lossy(&empty.stdout).contains("├─ empty/\n"));
+    assert!(
+        long.contains(" ├─ nested/ … 2 entries\n"),
+        "long output: {long}"
+    );
+    assert_eq!(zero.status.code(), Some(5));
+    assert!(
+        String::from_utf8_lossy(&zero.stderr).contains("expected a depth of at least 1"),
+        "{zero:?}"
+    );
+    // A file lx cannot load stops the listing rather than being skipped
+    assert_eq!(zero_listing.status.code(), Some(5));
+    assert!(zero_listing.stdout.is_empty(), "{zero_listing:?}");
+    assert!(
+        String::from_utf8_lossy(&zero_listing.stderr).contains(&format!(
+            "invalid config file '{}': invalid value",
+            config_path.display()
+        )),
+        "{zero_listing:?}"
+    );
+}
+
+#[test]
+fn closed_pipe_ends_listing_quietly() {
+    let temp_dir = create_temp_dir("closed-pipe");
+    // Enough output to overflow the pipe buffer however quickly it is closed
+    for index in 0..2000 {
+        fs::write(
+            temp_dir.join(format!("file-with-a-fairly-long-name-{index:04}")),
+            "",
+        )
+        .expect("create file");
+    }
+
+    let statuses = [&["-1"][..], &["-r"], &["-l"]].map(|args| {
+        let mut child = lx_command(None)
+            .args(args)
+            .arg(&temp_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("run lx");
+        drop(child.stdout.take());
+        child.wait_with_output().expect("wait for lx")
+    });
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    for output in statuses {
+        assert_eq!(output.status.code(), Some(141));
+        assert!(
+            output.stderr.is_empty(),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn format_selects_formatters_by_flag_and_config() {
+    let temp_dir = create_temp_dir("format");
+    fs::create_dir(temp_dir.join("src")).expect("create src");
+    fs::write(temp_dir.join("src/lib.rs"), "").expect("create lib.rs");
+    fs::write(temp_dir.join("notes.txt"), "").expect("create notes.txt");
+    let config_path = temp_dir.join("config.toml");
+    fs::write(&config_path, "[display]\nformat = \"one-per-line\"\n").expect("write config");
+    let bad_config_path = temp_dir.join("bad.toml");
+    fs::write(&bad_config_path, "[display]\nformat = \"tre\"\n").expect("write bad config");
+
+    let run = |config: Option<&Path>, args: &[&str]| {
+        lx_command(config)
+            .args(["--icons=never"])
+            .args(args)
+            .arg(&temp_dir)
+            .output()
+            .expect("run lx")
+    };
+    let configured = run(Some(&config_path), &[]);
+    let tree = run(Some(&config_path), &["--format", "tree"]);
+    let unknown = run(None, &["--format", "wide"]);
+    let conflicting = run(None, &["--format", "long", "-1"]);
+    let checked = lx_command(Some(&bad_config_path))
+        .arg("--check-config")
+        .output()
+        .expect("run lx");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert_eq!(
+        String::from_utf8_lossy(&configured.stdout),
+        "src\nbad.toml\nconfig.toml\nnotes.txt\n"
+    );
+    assert!(
+        String::from_utf8_lossy(&tree.stdout).ends_with("╰─ src/\n   ╰─ lib.rs\n"),
+        "tree output: {}",
+        String::from_utf8_lossy(&tree.stdout)
+    );
+    assert_eq!(unknown.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&unknown.stderr).contains("unknown format 'wide'"));
+    assert!(!conflicting.status.success());
+    assert!(
+        String::from_utf8_lossy(&checked.stderr)
+            .contains("unknown format `tre` (did you mean `tree`?)")
+    );
+}
+
+#[test]
+fn errors_use_distinct_exit_statuses() {
+    let temp_dir = create_temp_dir("exit-statuses");
+    fs::write(temp_dir.join("notes.txt"), "").expect("create notes.txt");
+    let missing = temp_dir.join("missing");
+
+    let not_found = lx_command(None).arg(&missing).output().expect("run lx");
+    let pattern = lx_command(None)
+        .args(["-x", "src/main.rs"])
+        .arg(&temp_dir)
+        .output()
+        .expect("run lx");
+    let profile = lx_command(None)
+        .args(["--profile", "audit"])
+        .arg(&temp_dir)
+        .output()
+        .expect("run lx");
+    // A bad pattern in the config is only warned about, and still listed
+    let config_path = temp_dir.join("config.toml");
+    fs::write(&config_path, "[display]\nexclude = [\"build/out\"]\n").expect("write config");
+    let config_pattern = lx_command(Some(&config_path))
+        .arg(&temp_dir)
+        .output()
+        .expect("run lx");
+    let check_config_pattern = lx_command(Some(&config_path))
+        .arg("--check-config")
+        .output()
+        .expect("run lx");
+    let output = fs::File::create("/dev/full").ok().map(|full| {
+        lx_command(None)
+            .arg(&temp_dir)
+            .stdout(full)
+            .output()
+            .expect("run lx")
+    });
+    let blocked_dir = temp_dir.join("blocked");
+    fs::create_dir(&blocked_dir).expect("create blocked directory");
+    fs::set_permissions(&blocked_dir, fs::Permissions::from_mode(0o000))
+        .expect("make directory unreadable");
+    // Permissions do not apply to root, so there is nothing to test then
+    let denied = fs::read_dir(&blocked_dir)
+        .is_err()
+        .then(|| lx_command(None).arg(&blocked_dir).output().expect("run lx"));
+    fs::set_permissions(&blocked_dir, fs::Permissions::from_mode(0o700))
+        .expect("restore directory permissions");
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert_eq!(not_found.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&not_found.stderr),
+        format!(
+            "lx: cannot access '{}': No such file or directory\n",
+            missing.display()
+        )
+    );
+    assert_eq!(pattern.status.code(), Some(6));
+    assert_eq!(
+        String::from_utf8_lossy(&pattern.stderr),
+        "lx: invalid exclude pattern 'src/main.rs': patterns match names, not paths\n"
+    );
+    assert_eq!(profile.status.code(), Some(5));
+    assert!(config_pattern.status.success(), "{config_pattern:?}");
+    assert!(String::from_utf8_lossy(&config_pattern.stdout).contains("notes.txt"));
+    assert!(
+        String::from_utf8_lossy(&config_pattern.stderr)
+            .contains("2:12: invalid exclude pattern `build/out`: patterns match names, not paths"),
+        "{config_pattern:?}"
+    );
+    assert_eq!(check_config_pattern.status.code(), Some(5));
+    if let Some(output) = output {
+        assert_eq!(output.status.code(), Some(7));
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("lx: write error: "));
+    }
+    if let Some(denied) = denied {
+        assert_eq!(denied.status.code(), Some(3));
+        assert_eq!(
+            String::from_utf8_lossy(&denied.stderr),
+            format!(
+                "lx: cannot open directory '{}': Permission denied\n",
+                blocked_dir.display()
+            )
+        );
+    }
 }
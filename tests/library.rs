@@ -0,0 +1,156 @@
+use lx_cli::filter::EntryFilter;
+use lx_cli::formatter::{TreeFormatter, TreeRenderer, flatten_tree};
+use lx_cli::sort::DefaultSort;
+use lx_cli::{
+    Config, LxError, MetadataMode, SortOptions, TreeOptions, read_target, write_json,
+    write_json_tree, write_long, write_one_per_line, write_recursive,
+};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[test]
+fn formatters_render_into_any_writer() {
+    let unique = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before epoch")
+        .as_nanos();
+    let temp_dir = std::env::temp_dir().join(format!("lx-cli-library-{unique}"));
+    fs::create_dir_all(temp_dir.join("src")).expect("create temp dir");
+    fs::write(temp_dir.join("Cargo.toml"), "").expect("create Cargo.toml");
+
+    colored::control::set_override(false);
+    let mut config = Config::default();
+    config.icons.clear();
+    let entries = read_target(&temp_dir, false, MetadataMode::Full)
+        .expect("read temp dir")
+        .value;
+    let sort = SortOptions::default();
+    let mut one_per_line = Vec::new();
+    write_one_per_line(&mut one_per_line, entries.clone(), &config, sort).expect("render");
+    let mut long = Vec::new();
+    write_long(&mut long, entries.clone(), &config, sort).expect("render");
+    let mut json = Vec::new();
+    write_json(&mut json, entries, sort, true).expect("render");
+    let filter = EntryFilter::new(false, false, Vec::new());
+    let options = TreeOptions {
+        max_depth: Some(1),
+        ..TreeOptions::new(&filter, sort)
+    };
+    let mut tree = Vec::new();
+    let tree_problems =
+        write_recursive(&mut tree, &temp_dir, &config, &TreeFormatter, &options).expect("render");
+    let mut json_tree = Vec::new();
+    let json_tree_problems =
+        write_json_tree(&mut json_tree, &temp_dir, &options, true).expect("render");
+    let long = String::from_utf8_lossy(&long);
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert_eq!(String::from_utf8_lossy(&one_per_line), "src\nCargo.toml\n");
+    assert!(
+        long.lines().count() == 2 && long.ends_with(" Cargo.toml\n"),
+        "{long}"
+    );
+    assert_eq!(json.split(|&byte| byte == b'\n').count(), 3);
+    assert!(tree_problems.is_empty() && json_tree_problems.is_empty());
+    assert!(
+        String::from_utf8_lossy(&tree).ends_with("├─ Cargo.toml\n╰─ src/\n"),
+        "{}",
+        String::from_utf8_lossy(&tree)
+    );
+    assert_eq!(json_tree.split(|&byte| byte == b'\n').count(), 3);
+}
+
+#[test]
+fn parallel_tree_matches_sequential_tree() {
+    let unique = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before epoch")
+        .as_nanos();
+    let temp_dir = std::env::temp_dir().join(format!("lx-cli-parallel-{unique}"));
+    for outer in 0..8 {
+        for inner in 0..8 {
+            let directory = temp_dir.join(format!("dir-{outer}/sub-{inner}"));
+            fs::create_dir_all(&directory).expect("create dirs");
+            fs::write(directory.join(format!("file-{outer}-{inner}")), "").expect("create file");
+        }
+    }
+
+    let filter = EntryFilter::new(false, false, Vec::new());
+    let collect = |threads| {
+        let tree = TreeRenderer::new(&filter, SortOptions::default(), DefaultSort::Name)
+            .with_threads(threads)
+            .collect_tree(&temp_dir, false, MetadataMode::Full)
+            .expect("collect tree")
+            .value
+            .expect("root is not filtered");
+        flatten_tree(tree.children.unwrap_or_default(), "ascii", false)
+            .into_iter()
+            .map(|tree_entry| {
+                let entry = tree_entry.entry;
+                format!(
+                    "{}{} {}:{}",
+                    tree_entry.branch,
+                    entry.path.display(),
+                    entry.owner,
+                    entry.group
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let sequential = collect(1);
+    let parallel = collect(8);
+    let entries = read_target(&temp_dir, false, MetadataMode::Full)
+        .expect("read temp dir")
+        .value;
+
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+
+    assert_eq!(sequential.len(), 8 + 8 * 8 * 2);
+    assert!(sequential[0].starts_with("├─dir-0 "));
+    assert!(sequential[1].starts_with("│  ├─sub-0 "));
+    // Workers look owners up at the same time, which must not mix up names
+    let owner = format!(" {}:{}", entries[0].owner, entries[0].group);
+    assert!(parallel.iter().all(|line| line.ends_with(&owner)));
+    assert_eq!(parallel, sequential);
+}
+
+#[test]
+fn tree_returns_unreadable_directories_as_problems() {
+    let unique = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before epoch")
+        .as_nanos();
+    let temp_dir = std::env::temp_dir().join(format!("lx-cli-problems-{unique}"));
+    let blocked_dir = temp_dir.join("blocked");
+    fs::create_dir_all(&blocked_dir).expect("create blocked directory");
+    fs::write(temp_dir.join("notes.txt"), "").expect("create file");
+    fs::set_permissions(&blocked_dir, fs::Permissions::from_mode(0o000))
+        .expect("make directory unreadable");
+    // Permissions do not apply to root, so there is nothing to test
+    let is_unreadable = fs::read_dir(&blocked_dir).is_err();
+
+    let filter = EntryFilter::new(false, false, Vec::new());
+    let tree = TreeRenderer::new(&filter, SortOptions::default(), DefaultSort::Name)
+        .collect_tree(&temp_dir, false, MetadataMode::Basic)
+        .expect("collect tree");
+
+    fs::set_permissions(&blocked_dir, fs::Permissions::from_mode(0o700))
+        .expect("restore directory permissions");
+    fs::remove_dir_all(&temp_dir).expect("remove temp dir");
+    if !is_unreadable {
+        return;
+    }
+
+    let children = tree
+        .value
+        .and_then(|root| root.children)
+        .expect("root has children");
+    assert_eq!(children.len(), 2);
+    assert_eq!(children[0].error.as_deref(), Some("Permission denied"));
+    assert!(matches!(
+        tree.problems.as_slice(),
+        [LxError::PermissionDenied { path, .. }] if *path == blocked_dir
+    ));
+}